@@ -0,0 +1,183 @@
+/////////////////////////// COMPILER //////////////////////////////////////////////////////////////
+
+use std::ops::Deref;
+use std::rc::Rc;
+use crate::interpreter::Bindings;
+use crate::name::Name;
+use crate::op::Op;
+use crate::parsed::Parsed;
+use crate::span::Span;
+use crate::stack_error::StackError;
+use crate::types::{Params, Signature};
+
+/// Compiled code, run by the VM. It is shared rather than copied by the functions and
+/// iterations that run it.
+pub type Code = Rc<Block>;
+
+/// The instructions compiled from a program, function or quotation.
+pub struct Block {
+    instrs: Box<[Instr]>,
+    /// Whether the code binds names, so that each run of it needs a scope of its own.
+    /// Code that binds nothing runs in the scope it was written in.
+    pub binds: bool,
+    /// Whether the code or any quotation written in it assigns names. A function with
+    /// such a body is called in a scope of its own, so that its assignments do not reach
+    /// the global names.
+    pub assigns: bool,
+}
+
+impl Deref for Block {
+    type Target = [Instr];
+
+    fn deref(&self) -> &[Instr] {
+        &self.instrs
+    }
+}
+
+/// A modifier an op takes from the code following it.
+pub struct Modifier {
+    /// The modifier as written.
+    pub value: Parsed,
+    /// Slot of the binding that replaces the modifier, if it is a symbol.
+    pub slot: Option<usize>,
+    /// Compiled contents of the modifier, if it is a quotation.
+    pub code: Option<Code>,
+}
+
+/// An instruction of compiled code.
+pub enum Instr {
+    /// Pushes a value that evaluates to itself.
+    Push(Parsed),
+    /// Pushes a list, replacing the symbols at the given positions by the values bound
+    /// to them, and giving the quotations at the given positions the current scope.
+    List { items: Rc<Vec<Parsed>>, symbols: Vec<(usize, usize)>, quotations: Vec<usize> },
+    /// Calls the function bound to a name or pushes the value bound to it, looking in
+    /// the current scope before the global slot. Unbound symbols push themselves.
+    Symbol { slot: usize, name: Name, symbol: Parsed },
+    /// Runs a builtin op, with its signature and modifiers found when compiling.
+    Op { op: Op, signature: Signature, modifiers: Vec<Modifier>, token: Parsed },
+    /// Stops the program, leaving an error value from the input on the stack without
+    /// reporting it, like the interpreter does.
+    Stop(StackError),
+    /// Fails with an error found when compiling, once the program gets to it.
+    Fail { error: StackError, token: Parsed },
+}
+
+impl Instr {
+    /// Returns the value the instruction was compiled from, which errors are reported at.
+    pub fn token(&self) -> Parsed {
+        match self {
+            Instr::Push(value) => value.clone(),
+            Instr::List { items, .. } => Parsed::List(Rc::clone(items)),
+            Instr::Symbol { symbol, .. } => symbol.clone(),
+            Instr::Op { token, .. } | Instr::Fail { token, .. } => token.clone(),
+            Instr::Stop(err) => Parsed::Error(err.clone()),
+        }
+    }
+}
+
+/// Compiles parsed code for the VM. Symbols are given their binding slots, ops have
+/// their signatures looked up and take their modifiers from the code following them,
+/// and quotations given as modifiers are compiled along with the code.
+///
+/// Names are bound while the program runs, so symbols are compiled to slots whether
+/// they are bound yet or not.
+///
+/// # Examples
+///
+/// ```
+/// use bprog::compiler::{compile, Instr};
+/// use bprog::interpreter::Bindings;
+/// use bprog::lexer::to_tokens;
+/// use bprog::parsing::parse;
+///
+/// let mut bindings = Bindings::default();
+/// let parsed = parse(&mut to_tokens("3 times { x 1 + }")).unwrap();
+/// let code = compile(parsed, &mut bindings);
+///
+/// assert_eq!(2, code.len());
+/// assert!(matches!(&code[1], Instr::Op { modifiers, .. } if modifiers[0].code.is_some()));
+/// assert_eq!(bindings.slot("x"), bindings.slot("x"));
+/// ```
+pub fn compile(input: impl IntoIterator<Item = Parsed>, bindings: &mut Bindings) -> Code {
+    let mut input = input.into_iter();
+    let mut code = vec![];
+    while let Some(p) = input.next() {
+        code.push(match p {
+            Parsed::Symbol(name, span) => Instr::Symbol {
+                slot: bindings.slot(&name),
+                name: name.clone(),
+                symbol: Parsed::Symbol(name, span),
+            },
+            Parsed::List(items) => {
+                let symbols = items.iter().enumerate()
+                    .filter_map(|(i, item)| match item {
+                        Parsed::Symbol(name, _) => Some((i, bindings.slot(name))),
+                        _ => None,
+                    })
+                    .collect();
+                let quotations = items.iter().enumerate()
+                    .filter(|(_, item)| matches!(item, Parsed::Quotation(..)))
+                    .map(|(i, _)| i)
+                    .collect();
+                Instr::List { items, symbols, quotations }
+            },
+            Parsed::Function(op, span) => compile_op(op, span, &mut input, bindings),
+            Parsed::Error(err) => Instr::Stop(err),
+            value => Instr::Push(value),
+        });
+    }
+    let binds = code.iter()
+        .any(|instr| matches!(instr, Instr::Op { op: Op::Assign | Op::AssignFunc | Op::Locals(_) | Op::Import | Op::Use, .. }));
+    let assigns = code.iter().any(|instr| match instr {
+        Instr::Op { op: Op::Assign | Op::AssignFunc, .. } => true,
+        Instr::Op { modifiers, .. } => modifiers.iter().any(|modifier| match &modifier.code {
+            Some(code) => code.assigns,
+            None => assigns_in(&modifier.value),
+        }),
+        Instr::Push(value) => assigns_in(value),
+        Instr::List { items, .. } => items.iter().any(assigns_in),
+        _ => false,
+    });
+    Rc::new(Block { instrs: code.into(), binds, assigns })
+}
+
+/// Returns whether a value is an assignment, or a quotation or list holding one.
+fn assigns_in(value: &Parsed) -> bool {
+    match value {
+        Parsed::Function(Op::Assign | Op::AssignFunc, _) => true,
+        Parsed::Quotation(items, _) => items.iter().any(assigns_in),
+        Parsed::List(items) => items.iter().any(assigns_in),
+        _ => false,
+    }
+}
+
+fn compile_op(op: Op, span: Span, input: &mut impl Iterator<Item = Parsed>, bindings: &mut Bindings) -> Instr {
+    let signature = op.get_signature();
+    let count = match signature.modifiers {
+        Params::Nullary => 0,
+        Params::Unary(_) => 1,
+        Params::Binary(_, _) => 2,
+        _ => panic!("closure arguments defined for max 2 quotations")
+    };
+    let modifiers: Vec<Modifier> = input.take(count)
+        .map(|value| compile_modifier(&op, value, bindings))
+        .collect();
+    let token = Parsed::Function(op.clone(), span);
+    if modifiers.len() < count {
+        return Instr::Fail { error: StackError::PrematureEnd, token };
+    }
+    Instr::Op { op, signature, modifiers, token }
+}
+
+fn compile_modifier(op: &Op, value: Parsed, bindings: &mut Bindings) -> Modifier {
+    let slot = match &value {
+        Parsed::Symbol(name, _) if !op.quotes_modifier() => Some(bindings.slot(name)),
+        _ => None,
+    };
+    let code = match &value {
+        Parsed::Quotation(q, _) => Some(compile(q.iter().cloned(), bindings)),
+        _ => None,
+    };
+    Modifier { value, slot, code }
+}
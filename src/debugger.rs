@@ -0,0 +1,296 @@
+/////////////////////////// DEBUGGER ////////////////////////////////////////////////////////////////
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::rc::Rc;
+use crate::interpreter::{Binding, Env};
+use crate::lexer::tokenize;
+use crate::name::Name;
+use crate::parsed::Parsed;
+use crate::parsing::parse;
+use crate::span::{Source, Span};
+
+/// Shows text to the user and reads the next command, or returns None once there are
+/// no more commands to read.
+pub type Prompt = Box<dyn FnMut(&str) -> Option<String>>;
+
+/// Number of pending tokens shown at a stop.
+const SHOWN_INPUT: usize = 8;
+
+const HELP: &str = "debugger commands:\n\
+    \ts, step          - Runs the next token, stopping inside any function it calls.\n\
+    \tn, next          - Runs the next token and any function it calls.\n\
+    \to, out           - Runs until the function running returns.\n\
+    \tc, continue      - Runs until a breakpoint is reached.\n\
+    \tb [name | line]  - Sets a breakpoint on a symbol or op, or on a line as line or file:line,\n\
+    \t                   or lists the breakpoints.\n\
+    \td <name | line>  - Deletes a breakpoint.\n\
+    \tp <name>         - Shows the value bound to a name.\n\
+    \tset <name> <val> - Binds a name to a value where it is bound, or in the code running.\n\
+    \tw, where         - Shows where the program stopped again.\n\
+    \th, help          - Shows debugger commands.\n\
+    An empty command steps.";
+
+/// Where the debugger stops a program, other than after a step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    /// Before a symbol or op of the name runs.
+    Symbol(Name),
+    /// Before the first token run of a line, or of a run of code starting on the line.
+    /// Lines of any source match unless a file is given.
+    Line { file: Option<String>, line: usize },
+}
+
+impl Breakpoint {
+    fn matches(&self, token: &Parsed, entering: bool) -> bool {
+        match (self, token) {
+            (Breakpoint::Symbol(name), Parsed::Symbol(symbol, _)) => name == symbol,
+            (Breakpoint::Symbol(name), Parsed::Function(op, _)) => name.as_str() == op.to_string(),
+            (Breakpoint::Line { file, line }, token) => entering && token.span().is_some_and(|span| {
+                span.line == *line && file.as_ref().is_none_or(|file| Path::new(span.file()).ends_with(file))
+            }),
+            _ => false,
+        }
+    }
+}
+
+impl std::str::FromStr for Breakpoint {
+    type Err = String;
+
+    /// Parses a line number, a `file:line`, or else the name of a symbol or op.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(line) = s.parse() {
+            return Ok(Breakpoint::Line { file: None, line });
+        }
+        match s.rsplit_once(':').map(|(file, line)| (file, line.parse())) {
+            Some((file, Ok(line))) if !file.is_empty() => Ok(Breakpoint::Line { file: Some(file.to_string()), line }),
+            _ if s.is_empty() => Err("expected a name or a line".to_string()),
+            _ => Ok(Breakpoint::Symbol(Name::new(s))),
+        }
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Symbol(name) => write!(f, "{}", name),
+            Breakpoint::Line { file: Some(file), line } => write!(f, "{}:{}", file, line),
+            Breakpoint::Line { file: None, line } => write!(f, "line {}", line),
+        }
+    }
+}
+
+/// The token a program is about to run, and the code it is found in.
+pub(crate) struct Position<'a> {
+    pub(crate) token: &'a Parsed,
+    pub(crate) code: &'a Rc<VecDeque<Parsed>>,
+    pub(crate) pc: usize,
+    /// Number of frames the interpreter is running, the code being the last of them.
+    pub(crate) depth: usize,
+}
+
+/// What a program looks like where it stopped.
+pub struct Stop {
+    pub token: Parsed,
+    /// The stack, top first.
+    pub stack: String,
+    /// Tokens of the code running left after the token.
+    pub input: Vec<Parsed>,
+    /// Names of the user functions running, outermost first.
+    pub calls: Vec<Name>,
+}
+
+impl Display for Stop {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let next = format!("next  > {}", self.token);
+        match self.token.span() {
+            Some(span) => writeln!(f, "{}", span.report(&next))?,
+            None => writeln!(f, "{}", next)?,
+        }
+        let mut input: Vec<String> = self.input.iter().take(SHOWN_INPUT).map(|p| p.to_string()).collect();
+        if self.input.len() > SHOWN_INPUT {
+            input.push("...".to_string());
+        }
+        writeln!(f, "stack > {}", self.stack)?;
+        writeln!(f, "input > {}", input.join(" "))?;
+        let calls: Vec<&str> = self.calls.iter().map(|name| name.as_str()).collect();
+        write!(f, "calls > {}", calls.join(" > "))
+    }
+}
+
+/// How the program runs until the debugger stops it next.
+enum Resume {
+    /// Stop before the next token.
+    Step,
+    /// Stop before the next token of the code at a depth once past a position in it,
+    /// or before any token of the code it returns to.
+    Over { depth: usize, code: Rc<VecDeque<Parsed>>, pc: usize },
+    /// Stop before any token of the code the frame at a depth returns to.
+    Out { depth: usize },
+    /// Stop only at breakpoints.
+    Continue,
+}
+
+/// Interactive debugger, stopping programs run by the reference interpreter before
+/// tokens of their code run. At each stop it shows the stack, the input left of the
+/// code running and the user functions running, and reads commands that inspect and
+/// change bindings, set breakpoints, and resume the program.
+///
+/// A debugger starts by stopping at the first token of the program. Once its prompt
+/// has no more commands, it lets the program run to the end.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::VecDeque;
+/// use bprog::debugger::Debugger;
+/// use bprog::interpreter::{run, Env};
+/// use bprog::lexer::to_tokens;
+/// use bprog::parsing::parse;
+/// use bprog::stack::Stack;
+///
+/// let mut commands = VecDeque::from(["n", "n", "n", "set x 5", "c"]);
+/// let mut env = Env::new();
+/// env.debugger = Some(Debugger::new(Box::new(move |_| commands.pop_front().map(String::from))));
+/// let mut stack = Stack::new();
+/// let parsed = parse(&mut to_tokens("x 1 := x 10 *")).unwrap();
+///
+/// run(&mut stack, &mut VecDeque::from(parsed), &mut env, true);
+/// assert_eq!("50", stack.contents_to_string());
+/// ```
+pub struct Debugger {
+    pub breakpoints: Vec<Breakpoint>,
+    resume: Resume,
+    prompt: Prompt,
+    /// Line and source of the last token run that has a span.
+    line: Option<(usize, Option<Rc<Source>>)>,
+}
+
+impl Debugger {
+    pub fn new(prompt: Prompt) -> Debugger {
+        Debugger { breakpoints: vec![], resume: Resume::Step, prompt, line: None }
+    }
+
+    /// Makes the debugger stop at the next token, as it does when it starts.
+    pub fn restart(&mut self) {
+        self.resume = Resume::Step;
+    }
+
+    /// Returns whether to stop before the token at a position.
+    pub(crate) fn stops_at(&mut self, at: &Position) -> bool {
+        let span = at.token.span();
+        let entering = at.pc == 0 || span.is_some_and(|span| !self.on_line(span));
+        if let Some(span) = span {
+            self.line = Some((span.line, span.source.clone()));
+        }
+        let stepped = match &self.resume {
+            Resume::Step => true,
+            Resume::Over { depth, code, pc } => at.depth < *depth
+                || (at.depth == *depth && Rc::ptr_eq(code, at.code) && at.pc > *pc),
+            Resume::Out { depth } => at.depth < *depth,
+            Resume::Continue => false,
+        };
+        stepped || self.breakpoints.iter().any(|breakpoint| breakpoint.matches(at.token, entering))
+    }
+
+    fn on_line(&self, span: &Span) -> bool {
+        match (&self.line, &span.source) {
+            (Some((line, Some(source))), Some(other)) => *line == span.line && Rc::ptr_eq(source, other),
+            (Some((line, None)), None) => *line == span.line,
+            _ => false,
+        }
+    }
+
+    /// Shows where the program stopped and runs commands until one resumes it. `caller`
+    /// is the depth of the frame of the innermost user function running, or 0.
+    pub(crate) fn stop(&mut self, at: &Position, stop: &Stop, caller: usize, env: &mut Env) {
+        let mut text = stop.to_string();
+        loop {
+            let Some(line) = (self.prompt)(&text) else {
+                self.breakpoints.clear();
+                self.resume = Resume::Continue;
+                return;
+            };
+            let line = line.trim();
+            let (command, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let arg = arg.trim();
+            self.resume = match command {
+                "" | "s" | "step" => Resume::Step,
+                "n" | "next" => Resume::Over { depth: at.depth, code: at.code.clone(), pc: at.pc },
+                "o" | "out" => Resume::Out { depth: caller },
+                "c" | "continue" => Resume::Continue,
+                command => {
+                    text = self.command(command, arg, stop, env);
+                    continue;
+                },
+            };
+            return;
+        }
+    }
+
+    /// Runs a command that does not resume the program, returning the text to show.
+    fn command(&mut self, command: &str, arg: &str, stop: &Stop, env: &mut Env) -> String {
+        match command {
+            "b" | "break" if arg.is_empty() => match self.breakpoints.is_empty() {
+                true => "no breakpoints set".to_string(),
+                false => self.breakpoints.iter().map(|b| format!("breakpoint at {}", b)).collect::<Vec<_>>().join("\n"),
+            },
+            "b" | "break" => match arg.parse::<Breakpoint>() {
+                Ok(breakpoint) => {
+                    let text = format!("breakpoint at {}", breakpoint);
+                    if !self.breakpoints.contains(&breakpoint) {
+                        self.breakpoints.push(breakpoint);
+                    }
+                    text
+                },
+                Err(err) => err,
+            },
+            "d" | "delete" => match arg.parse::<Breakpoint>() {
+                Ok(breakpoint) if self.breakpoints.contains(&breakpoint) => {
+                    self.breakpoints.retain(|b| *b != breakpoint);
+                    format!("deleted breakpoint at {}", breakpoint)
+                },
+                Ok(breakpoint) => format!("no breakpoint at {}", breakpoint),
+                Err(err) => err,
+            },
+            "p" | "print" => show_binding(arg, env),
+            "set" => set_binding(arg, env),
+            "w" | "where" => stop.to_string(),
+            "h" | "help" => HELP.to_string(),
+            other => format!("unknown command `{}`, type h for help", other),
+        }
+    }
+}
+
+fn show_binding(name: &str, env: &Env) -> String {
+    if name.is_empty() {
+        return "expected a name".to_string();
+    }
+    match env.lookup(&Name::new(name)) {
+        Some(binding) if binding.function => format!("{} = {} (function)", name, binding.value),
+        Some(binding) if binding.constant => format!("{} = {} (constant)", name, binding.value),
+        Some(binding) => format!("{} = {}", name, binding.value),
+        None => format!("{} is not bound", name),
+    }
+}
+
+/// Binds a name to the value written after it, keeping whether it is a function or
+/// a constant if it is bound already.
+fn set_binding(arg: &str, env: &mut Env) -> String {
+    let Some((name, value)) = arg.split_once(char::is_whitespace) else {
+        return "expected a name and a value".to_string();
+    };
+    let value = match parse(&mut tokenize(&Source::new("<debugger>", value))) {
+        Ok(parsed) if parsed.len() == 1 => parsed.into_iter().next().unwrap(),
+        Ok(_) => return "expected a single value".to_string(),
+        Err(err) => return err.to_string(),
+    };
+    let name = Name::new(name);
+    let (function, constant) = env.lookup(&name)
+        .map_or((false, false), |binding| (binding.function, binding.constant));
+    let text = format!("{} = {}", name, value);
+    env.rebind(&name, Binding { function, constant, value });
+    text
+}
@@ -0,0 +1,206 @@
+/////////////////////////// FORMATTER /////////////////////////////////////////////////////////////
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use crate::lexer::{tokenize, tokenize_with_comments, Token, TokenKind};
+use crate::parse_error::ParseError;
+use crate::parsing::parse;
+use crate::span::Source;
+
+/// Maximum line width the formatter aims for.
+pub const WIDTH: usize = 80;
+/// Indentation added for each level of nesting.
+pub const INDENT: &str = "    ";
+
+/// A token or bracketed block of the program as written, along with the number of
+/// line breaks in front of it in the source.
+enum Node {
+    Atom { text: String, breaks: usize, comment: bool },
+    Block { open: String, close: String, children: Vec<Node>, breaks: usize, close_breaks: usize },
+}
+
+impl Node {
+    fn breaks(&self) -> usize {
+        match self {
+            Node::Atom { breaks, .. } | Node::Block { breaks, .. } => *breaks,
+        }
+    }
+
+    fn is_line_comment(&self) -> bool {
+        match self {
+            Node::Atom { text, comment, .. } => *comment && text.starts_with("--"),
+            Node::Block { .. } => false,
+        }
+    }
+
+    /// Returns the node on a single line, if it may be written that way. Blocks
+    /// are only kept on one line if they were written on one line and contain no
+    /// comments.
+    fn flat(&self) -> Option<String> {
+        match self {
+            Node::Atom { text, comment: false, .. } => Some(text.clone()),
+            Node::Atom { .. } => None,
+            Node::Block { open, close, children, close_breaks: 0, .. } => {
+                let mut line = open.clone();
+                for child in children {
+                    if child.breaks() > 0 {
+                        return None;
+                    }
+                    line.push(' ');
+                    line.push_str(&child.flat()?);
+                }
+                line.push(' ');
+                line.push_str(close);
+                Some(line)
+            },
+            Node::Block { .. } => None,
+        }
+    }
+}
+
+/// Rewrites a program into the canonical layout, see `format_source`. The program is
+/// attributed to an unnamed source.
+///
+/// # Examples
+///
+/// ```
+/// use bprog::formatter::format_program;
+///
+/// let formatted = format_program("square   {\n  dup *\n}  fun").unwrap();
+/// assert_eq!("square {\n    dup *\n} fun\n", formatted);
+/// assert_eq!(formatted, format_program(&formatted).unwrap());
+/// ```
+pub fn format_program(input: &str) -> Result<String, ParseError> {
+    format_source(&Source::new("<input>", input))
+}
+
+/// Rewrites the text of a source into the canonical layout.
+///
+/// Tokens are separated by single spaces, and line breaks are kept where the source
+/// has them, with runs of blank lines collapsed into one. Quotations and lists written
+/// over several lines, containing comments, or too long to fit on one line are broken
+/// up, with the brackets on lines of their own and the contents indented by four
+/// spaces. Lines longer than `WIDTH` are wrapped between tokens. Strings are written
+/// in the `" text "` form with escapes for quotes, backslashes and control characters.
+/// Comments are kept where they are.
+///
+/// Formatting never changes what the program does, and formatting formatted text
+/// gives the same text back.
+///
+/// # Errors
+///
+/// Returns the ParseError for the source if it cannot be parsed, as it is then not
+/// known how the program is meant to be structured.
+pub fn format_source(source: &Rc<Source>) -> Result<String, ParseError> {
+    parse(&mut tokenize(source))?;
+    let nodes = to_nodes(&mut tokenize_with_comments(source)).0;
+    let mut out = String::new();
+    write_nodes(&mut out, &nodes, 0, true);
+    let mut formatted = out.trim_end().to_string();
+    if !formatted.is_empty() {
+        formatted.push('\n');
+    }
+    Ok(formatted)
+}
+
+/// Builds nodes from tokens up to the bracket closing the current block, which is
+/// returned along with the nodes.
+fn to_nodes(tokens: &mut VecDeque<Token>) -> (Vec<Node>, Option<Token>) {
+    let mut nodes = vec![];
+    while let Some(t) = tokens.pop_front() {
+        let breaks = t.line_breaks;
+        match t.kind {
+            TokenKind::Word if t.text == "{" || t.text == "[" => {
+                let (children, close) = to_nodes(tokens);
+                let close = close.expect("brackets are balanced in parsed input");
+                nodes.push(Node::Block {
+                    open: t.text,
+                    close: close.text,
+                    children,
+                    breaks,
+                    close_breaks: close.line_breaks,
+                });
+            },
+            TokenKind::Word if t.text == "}" || t.text == "]" => return (nodes, Some(t)),
+            TokenKind::Word => nodes.push(Node::Atom { text: t.text, breaks, comment: false }),
+            TokenKind::String => nodes.push(Node::Atom { text: quote(&t.text), breaks, comment: false }),
+            TokenKind::Comment => nodes.push(Node::Atom { text: t.text, breaks, comment: true }),
+            TokenKind::Invalid(_) => {},
+        }
+    }
+    (nodes, None)
+}
+
+/// Writes a string literal that reads back as the given contents.
+fn quote(text: &str) -> String {
+    if text.is_empty() {
+        return "\"\"".to_string();
+    }
+    let mut quoted = "\" ".to_string();
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            '\0' => quoted.push_str("\\0"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push_str(" \"");
+    quoted
+}
+
+/// Number of characters on the last line written.
+fn column(out: &str) -> usize {
+    out.rsplit('\n').next().unwrap_or("").chars().count()
+}
+
+fn newline(out: &mut String, depth: usize, blank: bool) {
+    while out.ends_with(' ') {
+        out.pop();
+    }
+    out.push('\n');
+    if blank {
+        out.push('\n');
+    }
+    out.push_str(&INDENT.repeat(depth));
+}
+
+/// Writes a sequence of nodes at the given nesting depth. Unless `first` is set, the
+/// first node starts on a new line.
+fn write_nodes(out: &mut String, nodes: &[Node], depth: usize, first: bool) {
+    let mut must_break = !first;
+    for (i, node) in nodes.iter().enumerate() {
+        let flat = node.flat();
+        if must_break || (i > 0 && node.breaks() > 0) {
+            newline(out, depth, i > 0 && node.breaks() > 1);
+        } else if i > 0 {
+            // Nodes that would not fit on a line of their own either are not moved.
+            let fits = match &flat {
+                Some(line) => column(out) + 1 + line.chars().count() <= WIDTH
+                    || depth * INDENT.len() + line.chars().count() > WIDTH,
+                None => true,
+            };
+            // Comments trailing a line stay on it.
+            if fits || matches!(node, Node::Atom { comment: true, .. }) {
+                out.push(' ');
+            } else {
+                newline(out, depth, false);
+            }
+        }
+        must_break = node.is_line_comment();
+        match (node, flat) {
+            (Node::Atom { text, .. }, _) => out.push_str(text),
+            (_, Some(line)) if column(out) + line.chars().count() <= WIDTH => out.push_str(&line),
+            (Node::Block { open, close, children, .. }, _) => {
+                out.push_str(open);
+                write_nodes(out, children, depth + 1, false);
+                newline(out, depth, false);
+                out.push_str(close);
+            },
+        }
+    }
+}
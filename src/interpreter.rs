@@ -1,12 +1,22 @@
 use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::vec;
+use crate::compiler::Code;
+use crate::debugger::{Debugger, Position, Stop};
+use crate::module::{import, Modules};
+use crate::name::Name;
+use crate::numeric::{Numeric, OverflowPolicy};
 use crate::op::{Op};
 use crate::parsed::Parsed;
+use crate::random::Rng;
+use crate::scope::Scope;
 use crate::stack::Stack;
 use crate::stack_error::{arg_mismatch, StackError};
-use crate::types::{Params};
+use crate::types::{Params, Type};
 
 
 
+#[derive(Clone)]
 pub struct Binding {
     pub function: bool,
     pub constant: bool,
@@ -14,6 +24,172 @@ pub struct Binding {
 }
 
 
+/// The names bound by a program. Each name is given a numbered slot the first time it
+/// is seen, so that compiled code can look bindings up by slot instead of by name.
+#[derive(Default, Clone)]
+pub struct Bindings {
+    slots: HashMap<Name, usize>,
+    values: Vec<Option<Binding>>,
+    /// Code compiled from the bound values by the VM, cleared when a slot is rebound.
+    compiled: Vec<Option<Code>>,
+}
+
+impl Bindings {
+    /// Returns the slot of a name, giving it a new one if it has none yet.
+    pub fn slot(&mut self, name: &str) -> usize {
+        if let Some(slot) = self.slots.get(name) {
+            return *slot;
+        }
+        self.values.push(None);
+        self.compiled.push(None);
+        self.slots.insert(Name::new(name), self.values.len() - 1);
+        self.values.len() - 1
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Binding> {
+        self.slots.get(name).and_then(|slot| self.at(*slot))
+    }
+
+    /// Returns the binding in a slot, which must have been given out by `slot`.
+    pub fn at(&self, slot: usize) -> Option<&Binding> {
+        self.values[slot].as_ref()
+    }
+
+    pub fn insert(&mut self, name: &str, binding: Binding) {
+        let slot = self.slot(name);
+        self.values[slot] = Some(binding);
+        self.compiled[slot] = None;
+    }
+
+    pub fn compiled(&self, slot: usize) -> Option<&Code> {
+        self.compiled[slot].as_ref()
+    }
+
+    pub fn set_compiled(&mut self, slot: usize, code: Code) {
+        self.compiled[slot] = Some(code);
+    }
+
+    /// Puts back the values bound when `saved` was cloned from these bindings. Slots
+    /// given out since then are kept, unbound, as code compiled meanwhile refers to them.
+    pub fn restore(&mut self, saved: Bindings) {
+        let len = self.values.len();
+        self.values = saved.values;
+        self.values.resize(len, None);
+        self.compiled = saved.compiled;
+        self.compiled.resize(len, None);
+    }
+}
+
+
+/// What is left of the stack and the global bindings when a top-level input is stopped
+/// by an error.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OnError {
+    /// Put them back as they were before the input ran.
+    #[default]
+    Rollback,
+    /// Keep the bindings, and replace the stack by the value that caused the error.
+    Clear,
+}
+
+/// Number of nested calls allowed unless a program sets its own limit.
+pub const DEFAULT_DEPTH_LIMIT: usize = 1_000_000;
+
+/// Interpreter state shared by all code in a program: the names it has bound,
+/// settings it can query and change while running, and its random number generator.
+pub struct Env {
+    /// Names bound at the top level of the program, or in `global` quotations.
+    pub bindings: Bindings,
+    /// Scope of the code running, or None where names are bound globally.
+    pub scope: Option<Rc<Scope>>,
+    pub overflow: OverflowPolicy,
+    pub rng: Rng,
+    /// Largest number of nested calls, or 0 for no limit other than memory.
+    pub depth_limit: usize,
+    /// Modules imported by the program, and where to look for them.
+    pub modules: Modules,
+    pub on_error: OnError,
+    /// Debugger stopping the program as the reference interpreter runs it, if any.
+    pub debugger: Option<Debugger>,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Env {
+            bindings: Bindings::default(),
+            scope: None,
+            overflow: OverflowPolicy::default(),
+            rng: Rng::default(),
+            depth_limit: DEFAULT_DEPTH_LIMIT,
+            modules: Modules::default(),
+            on_error: OnError::default(),
+            debugger: None,
+        }
+    }
+}
+
+impl Env {
+    pub fn new() -> Env {
+        Env::default()
+    }
+
+    /// Returns the binding of a name in the scope of the code running or the scopes
+    /// around it, or else its global binding.
+    pub fn lookup(&self, name: &Name) -> Option<Binding> {
+        match self.scope.as_ref().and_then(|scope| scope.get(name)) {
+            Some(binding) => Some(binding),
+            None => self.bindings.get(name).cloned(),
+        }
+    }
+
+    /// Binds a name in the scope of the code running, or globally outside of any.
+    pub fn bind(&mut self, name: &Name, binding: Binding) {
+        match &self.scope {
+            Some(scope) => scope.bind(name.clone(), binding),
+            None => self.bindings.insert(name, binding),
+        }
+    }
+
+    /// Assigns a name, as `:=` and `fun` do. A name bound in the scope of the code
+    /// running or the scopes around it is rebound there, and a global name is rebound
+    /// by code written at the top level of the program. Otherwise the name is bound in
+    /// the scope of the code running, so functions and modules do not overwrite global
+    /// names unless they do so in `global`.
+    pub fn assign(&mut self, name: &Name, binding: Binding) {
+        match &self.scope {
+            Some(scope) if scope.binds(name) => {
+                scope.rebind(name, binding);
+            },
+            Some(scope) if !scope.at_top_level() || self.bindings.get(name).is_none() => scope.bind(name.clone(), binding),
+            _ => self.bindings.insert(name, binding),
+        }
+    }
+
+    /// Rebinds a name where it is bound, in the scope of the code running, the scopes
+    /// around it or globally, and otherwise binds it like `bind`.
+    pub fn rebind(&mut self, name: &Name, binding: Binding) {
+        match &self.scope {
+            Some(scope) if scope.binds(name) => {
+                scope.rebind(name, binding);
+            },
+            Some(scope) if self.bindings.get(name).is_none() => scope.bind(name.clone(), binding),
+            _ => self.bindings.insert(name, binding),
+        }
+    }
+
+    /// Makes `scope` the scope of the code running, unless it already is.
+    pub(crate) fn enter(&mut self, scope: &Option<Rc<Scope>>) {
+        let same = match (&self.scope, scope) {
+            (Some(current), Some(scope)) => Rc::ptr_eq(current, scope),
+            (current, scope) => current.is_none() && scope.is_none(),
+        };
+        if !same {
+            self.scope = scope.clone();
+        }
+    }
+}
+
+
 
 pub enum Args {
     Nullary,
@@ -24,65 +200,448 @@ pub enum Args {
 
 
 
-pub fn run(stack: &mut Stack<Parsed>, input: &mut VecDeque<Parsed>, bindings: &mut HashMap<String, Binding>, fatal: bool) {
-    while let Some(p) = input.pop_front() {
-        match p.clone() {
-            Parsed::Error(_) => {
-                stack.push(p);
-                break;
-            }
-            Parsed::Symbol(s) => {
-                if let Some (val) = bindings.get(&s) {
-                    if val.function {
-                        run(stack, &mut VecDeque::from(val.value.get_contents().unwrap()), bindings, fatal)
-                    } else {
-                        stack.push(val.value.clone())
-                    }
-                } else {
-                    stack.push(p.clone())
+/// A higher order function part way through its iterations, running a body of type `B`
+/// on the stack each time. The interpreter runs parsed bodies and the VM compiled ones.
+pub(crate) enum Iteration<B> {
+    /// Runs the body a number of times more.
+    Times { body: B, remaining: i128, token: Parsed },
+    /// Pushes each remaining element and runs the body on it, for each and foldl.
+    Each { body: B, items: vec::IntoIter<Parsed>, token: Parsed },
+    /// Runs the body on each remaining element like Each, then collects one value from
+    /// the top of the stack for each of the `count` elements into the result.
+    Map { body: B, items: vec::IntoIter<Parsed>, count: usize, into: Mapped, token: Parsed },
+    /// Runs the condition, and the body for as long as the condition leaves False on
+    /// the stack. `checking` is set while the condition runs.
+    Loop { condition: B, body: B, checking: bool, token: Parsed },
+    /// Runs the body, and if it fails, restores the stack and runs the handler on the
+    /// error caught.
+    Try { body: B, handler: B, guard: Guard, token: Parsed },
+    /// Runs the body and then the cleanup, whether the body failed or not. An error of
+    /// the body is raised again once the cleanup has run on the restored stack.
+    Ensure { body: B, cleanup: B, guard: Guard, token: Parsed },
+}
+
+/// What `map` makes of the values collected from the stack.
+pub(crate) enum Mapped {
+    List,
+    Just,
+    Ok,
+    /// `Nothing` or a `Failure`, which `map` leaves as it is without running its body.
+    Unchanged(Rc<Parsed>),
+}
+
+/// How far a `try` or `ensure` has got.
+pub(crate) enum Guard {
+    /// The body is yet to run.
+    Entering,
+    /// The body is running, and errors in it are caught. Holds the stack it started with.
+    Guarding(Stack<Parsed>),
+    /// The handler or cleanup is running, after the body failed with the given error,
+    /// if it did. Errors in it are not caught.
+    Handling(Option<Box<StackError>>),
+}
+
+impl<B: Clone> Iteration<B> {
+    /// Runs the body a number of times, or not at all for counts below 1.
+    pub(crate) fn times(count: Parsed, body: B, token: &Parsed) -> Result<Self, StackError> {
+        let remaining = match count {
+            Parsed::Num(Numeric::Integer(i)) => i,
+            Parsed::Num(n) if n < Numeric::Integer(0) => 0,
+            _ => return Err(StackError::Overflow),
+        };
+        Ok(Iteration::Times { body, remaining, token: token.clone() })
+    }
+
+    /// Runs the body on each element of a list, then collects one value from the top of
+    /// the stack for each element into a new list, the last element's value last. The
+    /// values of `Just` and `Ok` are mapped like a list of one element, while `Nothing`
+    /// and `Failure` are left as they are.
+    pub(crate) fn map(functor: Parsed, body: B, token: &Parsed) -> Self {
+        let (items, into) = match functor {
+            Parsed::List(items) => (Rc::unwrap_or_clone(items), Mapped::List),
+            Parsed::Maybe(Some(value)) => (vec![Rc::unwrap_or_clone(value)], Mapped::Just),
+            Parsed::Result(Ok(value)) => (vec![Rc::unwrap_or_clone(value)], Mapped::Ok),
+            unchanged @ (Parsed::Maybe(None) | Parsed::Result(Err(_))) => (vec![], Mapped::Unchanged(Rc::new(unchanged))),
+            _ => panic!("bug: invalid arguments sent to map function. Check constraints."),
+        };
+        Iteration::Map { body, count: items.len(), items: items.into_iter(), into, token: token.clone() }
+    }
+
+    /// Pushes each element of a list in turn and runs the body on it. Also used by foldl,
+    /// once the initial value is pushed, as the body then combines the two top values
+    /// into the next accumulated value.
+    pub(crate) fn each(list: Parsed, body: B, token: &Parsed) -> Self {
+        let Parsed::List(items) = list else {
+            panic!("bug: invalid arguments sent to each function. Check constraints.")
+        };
+        Iteration::Each { body, items: Rc::unwrap_or_clone(items).into_iter(), token: token.clone() }
+    }
+
+    /// Runs the condition, and the body for as long as the condition leaves False on the
+    /// stack. Any other number or boolean ends the loop.
+    pub(crate) fn until(condition: B, body: B, token: &Parsed) -> Self {
+        Iteration::Loop { condition, body, checking: false, token: token.clone() }
+    }
+
+    /// Runs the body, running the handler on any error it fails with instead.
+    pub(crate) fn try_catch(body: B, handler: B, token: &Parsed) -> Self {
+        Iteration::Try { body, handler, guard: Guard::Entering, token: token.clone() }
+    }
+
+    /// Runs the body and then the cleanup, whether the body fails or not.
+    pub(crate) fn ensure(body: B, cleanup: B, token: &Parsed) -> Self {
+        Iteration::Ensure { body, cleanup, guard: Guard::Entering, token: token.clone() }
+    }
+
+    /// Returns the function that started the iteration, which errors found between
+    /// iterations are reported at.
+    pub(crate) fn token(&self) -> &Parsed {
+        match self {
+            Iteration::Times { token, .. } | Iteration::Each { token, .. } |
+            Iteration::Map { token, .. } | Iteration::Loop { token, .. } |
+            Iteration::Try { token, .. } | Iteration::Ensure { token, .. } => token,
+        }
+    }
+
+    /// Catches an error raised while the body of a `try` or `ensure` runs, restoring
+    /// the stack it started with. Returns the code to run on the error, or None if the
+    /// iteration does not guard the code that failed.
+    pub(crate) fn catch(&mut self, err: &StackError, stack: &mut Stack<Parsed>) -> Option<B> {
+        let (guard, code, caught) = match self {
+            Iteration::Try { handler, guard, .. } => (guard, handler, Guard::Handling(None)),
+            Iteration::Ensure { cleanup, guard, .. } => (guard, cleanup, Guard::Handling(Some(Box::new(err.clone())))),
+            _ => return None,
+        };
+        let Guard::Guarding(saved) = std::mem::replace(guard, caught) else {
+            return None;
+        };
+        *stack = saved;
+        if let Guard::Handling(None) = guard {
+            stack.push(Parsed::Caught(err.clone()));
+        }
+        Some(code.clone())
+    }
+
+    /// Moves the iteration on, returning the code to run next, or None once it has
+    /// finished.
+    pub(crate) fn advance(&mut self, stack: &mut Stack<Parsed>) -> Option<B> {
+        match self {
+            Iteration::Times { body, remaining, .. } => {
+                if *remaining <= 0 {
+                    return None;
                 }
+                *remaining -= 1;
+                Some(body.clone())
             },
-            Parsed::List(s) => {
-                stack.push(Parsed::List(s.iter()
-                    .map(|p| resolve_symbol(p.clone(), bindings))
-                    .collect()));
-            }
-            Parsed::Function(op) => {
-                exec_op(&op, stack, input, bindings, fatal)
+            Iteration::Each { body, items, .. } => {
+                stack.push(items.next()?);
+                Some(body.clone())
             },
-            other => {
-                stack.push(other)
+            Iteration::Map { body, items, count, into, .. } => {
+                if let Some(item) = items.next() {
+                    stack.push(item);
+                    return Some(body.clone());
+                }
+                let Some(mut mapped) = stack.split_off(*count) else {
+                    stack.push(Parsed::Error(StackError::PopEmpty));
+                    return None;
+                };
+                stack.push(match into {
+                    Mapped::List => Parsed::list(mapped),
+                    Mapped::Just => Parsed::just(mapped.pop().expect("one value was mapped")),
+                    Mapped::Ok => Parsed::ok(mapped.pop().expect("one value was mapped")),
+                    Mapped::Unchanged(value) => value.as_ref().clone(),
+                });
+                None
+            },
+            Iteration::Loop { condition, body, checking, .. } => {
+                if !*checking {
+                    *checking = true;
+                    return Some(condition.clone());
+                }
+                *checking = false;
+                match stack.pop() {
+                    Some(Parsed::Bool(false)) => Some(body.clone()),
+                    Some(Parsed::Bool(true) | Parsed::Num(_)) => None,
+                    Some(other) => {
+                        stack.push(Parsed::Error(StackError::TypeMismatch(format!(
+                            "err: condition of type \x1b[33m{}\x1b[0m with value \x1b[33m{}\x1b[0m in the function \
+                             \x1b[36m{}\x1b[0m is not a \x1b[31mBool\x1b[0m.", other.get_type(), other, Op::Loop))));
+                        None
+                    },
+                    None => {
+                        stack.push(Parsed::Error(StackError::PopEmpty));
+                        None
+                    },
+                }
+            },
+            Iteration::Try { body, guard, .. } => match guard {
+                Guard::Entering => {
+                    *guard = Guard::Guarding(stack.clone());
+                    Some(body.clone())
+                },
+                _ => None,
+            },
+            Iteration::Ensure { body, cleanup, guard, .. } => match guard {
+                Guard::Entering => {
+                    *guard = Guard::Guarding(stack.clone());
+                    Some(body.clone())
+                },
+                Guard::Guarding(_) => {
+                    *guard = Guard::Handling(None);
+                    Some(cleanup.clone())
+                },
+                Guard::Handling(err) => {
+                    if let Some(err) = err.take() {
+                        stack.push(Parsed::Error(*err));
+                    }
+                    None
+                },
+            },
+        }
+    }
+}
+
+/// Code to run, with the scope it was written in, or None for the global scope. Each
+/// run of the code binds names in a new scope nested in that one.
+#[derive(Clone)]
+pub(crate) struct Body<C> {
+    pub(crate) code: C,
+    pub(crate) scope: Option<Rc<Scope>>,
+}
+
+/// Work left to do by the interpreter. Function calls, quotations and higher order
+/// functions push frames instead of recursing, so how deeply bprog code nests is only
+/// limited by memory and the depth limit of the Env.
+enum Frame {
+    /// A program, function or quotation, run from the given position on in a scope,
+    /// or None for the global scope. Function bodies and quotations are shared with
+    /// the values they came from. Function bodies keep the name they were called by.
+    Code { code: Rc<VecDeque<Parsed>>, pc: usize, scope: Option<Rc<Scope>>, call: Option<Name> },
+    Iteration(Iteration<Body<Rc<VecDeque<Parsed>>>>),
+}
+
+impl Frame {
+    /// Runs code in a new scope of its own.
+    fn new(body: Body<Rc<VecDeque<Parsed>>>) -> Frame {
+        Frame::Code { code: body.code, pc: 0, scope: Some(Scope::new(body.scope)), call: None }
+    }
+
+    /// Runs the body of the function bound to a name in a new scope of its own.
+    fn call(body: Body<Rc<VecDeque<Parsed>>>, name: &Name) -> Frame {
+        Frame::Code { code: body.code, pc: 0, scope: Some(Scope::call(body.scope)), call: Some(name.clone()) }
+    }
+
+    /// Runs code in the global scope, as the program itself and `global` quotations are.
+    fn global(code: Rc<VecDeque<Parsed>>) -> Frame {
+        Frame::Code { code, pc: 0, scope: None, call: None }
+    }
+}
+
+/// Runs the input on the stack. When an error occurs it is reported, panicking if
+/// `fatal` is set, and the stack and global bindings are rolled back to where they
+/// were before the input ran, or with `OnError::Clear` the stack is replaced by the
+/// value that caused it.
+///
+/// Calls are kept on a stack of frames rather than the Rust stack. A call made as the
+/// last thing a function or quotation does replaces it, so tail recursion runs in
+/// constant space, and other calls nest until the depth limit of the Env is reached.
+///
+/// This walks the parsed input directly, and is kept as the reference for `vm::run`,
+/// which compiles the input first.
+///
+/// # Returns
+///
+/// True if all of the input ran, or false if it was stopped by an error.
+pub fn run(stack: &mut Stack<Parsed>, input: &mut VecDeque<Parsed>, env: &mut Env, fatal: bool) -> bool {
+    let input = std::mem::take(input);
+    transaction(stack, env, fatal, |stack, env| run_in(stack, input, None, env, fatal))
+}
+
+/// Runs a top-level input against a snapshot of the stack and the global bindings,
+/// and puts the snapshot back if the input is stopped by an error. Nothing is saved
+/// when errors are fatal, or when the Env clears the stack on errors instead.
+pub(crate) fn transaction(stack: &mut Stack<Parsed>, env: &mut Env, fatal: bool,
+                          run: impl FnOnce(&mut Stack<Parsed>, &mut Env) -> bool) -> bool {
+    if fatal || env.on_error == OnError::Clear {
+        return run(stack, env);
+    }
+    let (saved_stack, saved_bindings) = (stack.clone(), env.bindings.clone());
+    let completed = run(stack, env);
+    if !completed {
+        *stack = saved_stack;
+        env.bindings.restore(saved_bindings);
+    }
+    completed
+}
+
+/// Runs the input like `run`, binding its names in `scope` rather than globally.
+pub(crate) fn run_in(stack: &mut Stack<Parsed>, input: VecDeque<Parsed>, scope: Option<Rc<Scope>>, env: &mut Env,
+                     fatal: bool) -> bool {
+    let mut frames = vec![Frame::Code { code: Rc::new(input), pc: 0, scope, call: None }];
+    loop {
+        if env.debugger.is_some() {
+            pause(&frames, stack, env);
+        }
+        let Some(frame) = frames.last_mut() else { break };
+        let (p, next) = match frame {
+            Frame::Code { code, pc, scope, .. } => match code.get(*pc).cloned() {
+                Some(Parsed::Error(err)) => {
+                    stack.push(Parsed::Error(err));
+                    return false;
+                },
+                Some(p) => {
+                    *pc += 1;
+                    env.enter(scope);
+                    let next = exec_token(&p, stack, code, pc, env, fatal);
+                    (p, next)
+                },
+                None => {
+                    frames.pop();
+                    continue;
+                },
+            },
+            Frame::Iteration(iteration) => {
+                let token = iteration.token().clone();
+                let next = iteration.advance(stack).map(Frame::new);
+                if next.is_none() {
+                    frames.pop();
+                }
+                (token, next)
+            },
+        };
+        if let Some(next) = next {
+            if let Err(err) = push_frame(&mut frames, next, env.depth_limit) {
+                stack.push(Parsed::Error(err));
             }
         }
-        if let Some(Parsed::Error(err)) = stack.top() {
-            if fatal { panic!("{}", err)} else { println!("{}", err)}
+        if let Some(err) = recover(&mut frames, stack, env.depth_limit) {
+            let message = err.report(p.span());
+            if fatal { panic!("{}", message)} else { println!("{}", message)}
             stack.clear();
             stack.push(p);
-            break;
+            return false;
         }
     }
+    true
 }
 
+/// Hands the program to the debugger before the next token of the code on top of the
+/// frames runs, if the debugger stops there.
+fn pause(frames: &[Frame], stack: &Stack<Parsed>, env: &mut Env) {
+    let Some(Frame::Code { code, pc, scope, .. }) = frames.last() else { return };
+    let Some(token) = code.get(*pc) else { return };
+    let Some(mut debugger) = env.debugger.take() else { return };
+    let at = Position { token, code, pc: *pc, depth: frames.len() };
+    if debugger.stops_at(&at) {
+        let calls: Vec<(usize, Name)> = frames.iter().enumerate()
+            .filter_map(|(i, frame)| match frame {
+                Frame::Code { call: Some(name), .. } => Some((i + 1, name.clone())),
+                _ => None,
+            })
+            .collect();
+        let caller = calls.last().map_or(0, |(depth, _)| *depth);
+        let stop = Stop {
+            token: token.clone(),
+            stack: stack.contents_to_string(),
+            input: code.iter().skip(*pc + 1).cloned().collect(),
+            calls: calls.into_iter().map(|(_, name)| name).collect(),
+        };
+        env.enter(scope);
+        debugger.stop(&at, &stop, caller, env);
+    }
+    env.debugger = Some(debugger);
+}
+
+/// Recovers from any error on top of the stack by unwinding the frames to the innermost
+/// `try` or `ensure` guarding the code that failed, and running its handler or cleanup.
+/// Returns the error if no frame guards the code.
+fn recover(frames: &mut Vec<Frame>, stack: &mut Stack<Parsed>, limit: usize) -> Option<StackError> {
+    while let Some(Parsed::Error(err)) = stack.top() {
+        let err = err.clone();
+        let handler = loop {
+            match frames.last_mut() {
+                Some(Frame::Iteration(iteration)) => if let Some(handler) = iteration.catch(&err, stack) {
+                    break handler;
+                },
+                Some(_) => {},
+                None => return Some(err),
+            }
+            frames.pop();
+        };
+        if let Err(err) = push_frame(frames, Frame::new(handler), limit) {
+            stack.push(Parsed::Error(err));
+        }
+    }
+    None
+}
+
+/// Pushes a frame, first dropping the frame on top if it is code that has finished.
+fn push_frame(frames: &mut Vec<Frame>, frame: Frame, limit: usize) -> Result<(), StackError> {
+    if let Some(Frame::Code { code, pc, .. }) = frames.last() {
+        if *pc == code.len() {
+            frames.pop();
+        }
+    }
+    if limit > 0 && frames.len() >= limit {
+        return Err(StackError::DepthLimit(limit));
+    }
+    frames.push(frame);
+    Ok(())
+}
+
+/// Runs a single value of the input, returning the frame of any code it calls. Ops
+/// take their modifiers from the input following the position `pc`.
+fn exec_token(p: &Parsed, stack: &mut Stack<Parsed>, input: &VecDeque<Parsed>, pc: &mut usize, env: &mut Env,
+              fatal: bool) -> Option<Frame> {
+    match p {
+        Parsed::Symbol(s, _) => {
+            if let Some (val) = env.lookup(s) {
+                if val.function {
+                    return iterate(stack, body_of(val.value, env).map(|body| Frame::call(body, s)));
+                }
+                stack.push(val.value)
+            } else {
+                stack.push(p.clone())
+            }
+        },
+        Parsed::List(s) => {
+            stack.push(Parsed::list(s.iter()
+                .map(|p| resolve(p.clone(), env))
+                .collect()));
+        }
+        Parsed::Function(op, _) => {
+            return exec_op(op, p, stack, input, pc, env, fatal)
+        },
+        other => {
+            stack.push(resolve(other.clone(), env))
+        }
+    }
+    None
+}
 
 
 
-fn exec_op(op: &Op, stack: &mut Stack<Parsed>, input: &mut VecDeque<Parsed>, bindings: &mut HashMap<String, Binding>, fatal: bool) {
+fn exec_op(op: &Op, token: &Parsed, stack: &mut Stack<Parsed>, input: &VecDeque<Parsed>, pc: &mut usize,
+           env: &mut Env, fatal: bool) -> Option<Frame> {
+    if let Op::Locals(names) = op {
+        bind_locals(names, stack, env);
+        return None;
+    }
     let signature = op.clone().get_signature();
     let mut arg  = Parsed::Error(StackError::PopEmpty);
     let mut arg2 = Parsed::Error(StackError::PopEmpty);
-    let mods: Args;
-    let ret;
-    match get_modifiers(&op, input, bindings) {
-        Ok(m) => mods = m,
-        Err(e) => {stack.push(Parsed::Error(e)); return;},
-    }
+    let mods = match get_modifiers(op, input, pc, env) {
+        Ok(m) => m,
+        Err(e) => {stack.push(Parsed::Error(e)); return None;},
+    };
 
     if let Params::Unary(_) | Params::Binary(_, _) = &signature.stack_args {
         if let Some(t) = stack.pop() {
             arg = t
         } else {
             stack.push(Parsed::Error(StackError::PopEmpty));
-            return;
+            return None;
         }
     }
     if let Params::Binary(_, _) = &signature.stack_args {
@@ -90,45 +649,181 @@ fn exec_op(op: &Op, stack: &mut Stack<Parsed>, input: &mut VecDeque<Parsed>, bin
             arg2 = t
         } else {
             stack.push(Parsed::Error(StackError::PopEmpty));
-            return;
+            return None;
         }
     }
-    match &signature.stack_args {
-        Params::Nullary => {
-            ret = op.exec_nullary(mods, bindings);
+    let ret = match &signature.stack_args {
+        Params::Nullary => match op {
+            Op::Loop => return iterate(stack, loop_frame(mods, token, env)),
+            Op::Global => return iterate(stack, global_frame(mods, env)),
+            Op::Try | Op::Ensure => return iterate(stack, guard_frame(op, mods, token, env)),
+            Op::Import | Op::Use => import(op, mods, token, env, run_in, fatal),
+            _ => op.exec_nullary(mods, env),
         },
         Params::Unary(c) => {
-            ret = if !c.is_satisfied_by(&arg.get_type()) {
-                Parsed::Error(arg_mismatch(&op, &Args::Unary(arg), true))
+            if !c.is_satisfied_by(&arg.get_type()) {
+                Parsed::Error(arg_mismatch(op, &Args::Unary(arg), true))
             } else {
-                op.exec_unary(arg, mods, bindings)
+                match op {
+                    Op::Times => return iterate(stack, times_frame(arg, mods, token, env)),
+                    Op::Map => return iterate(stack, map_frame(arg, mods, token, env)),
+                    Op::Each => return iterate(stack, each_frame(arg, mods, token, env)),
+                    Op::Case => {
+                        let frame = case_frame(arg, mods, stack, env);
+                        return iterate(stack, frame);
+                    },
+                    _ => op.exec_unary(arg, mods, env),
+                }
             }
         },
         Params::Binary(c1, c2) => {
             // Checks that the constraints of the function signature is satisfied.
-            ret = if !c1.is_satisfied_by(&arg2.get_type()) ||
+            if !c1.is_satisfied_by(&arg2.get_type()) ||
                 !c2.is_satisfied_by(&arg.get_type()) {
                 Parsed::Error(
-                         arg_mismatch(&op, &Args::Binary(arg2.clone(), arg.clone()), true))
+                         arg_mismatch(op, &Args::Binary(arg2.clone(), arg.clone()), true))
+            } else if op == &Op::Foldl {
+                stack.push(arg);
+                return iterate(stack, each_frame(arg2, mods, token, env));
             } else {
-                op.exec_binary(&arg2, &arg, mods, bindings)
+                op.exec_binary(arg2, arg, mods, env)
             }
         },
         _ => panic!("temary arguments not implemented")
-    }
+    };
 
     match ret {
-        Parsed::Quotation(q) => {
-            run(stack, &mut q.clone(), bindings, fatal)
-        },
+        Parsed::Quotation(q, _) if op.pushes_values() => q.iter().for_each(|p| stack.push(p.clone())),
+        Parsed::Quotation(code, scope) => return Some(Frame::new(Body { code, scope })),
         Parsed::Void => {},
         _ => stack.push(ret)
     }
+    None
+}
+
+
+
+/// Pops a value off the stack for each name of a quotation header and binds the names
+/// to them in the current scope, the last name to the top value.
+pub(crate) fn bind_locals(names: &[Name], stack: &mut Stack<Parsed>, env: &mut Env) {
+    match stack.split_off(names.len()) {
+        Some(values) => names.iter().zip(values).for_each(|(name, value)| {
+            env.bind(name, Binding { function: false, constant: false, value })
+        }),
+        None => stack.push(Parsed::Error(StackError::PopEmpty)),
+    }
+}
+
+
+/// Pushes the value held by a Maybe or Result for `case`, and tells whether it is Just
+/// or Ok. Nothing pushes no value, and a Failure pushes the reason it failed.
+pub(crate) fn open_case(value: Parsed, stack: &mut Stack<Parsed>) -> bool {
+    match value {
+        Parsed::Maybe(Some(value)) | Parsed::Result(Ok(value)) => {
+            stack.push(Rc::unwrap_or_clone(value));
+            true
+        },
+        Parsed::Result(Err(reason)) => {
+            stack.push(Rc::unwrap_or_clone(reason));
+            false
+        },
+        Parsed::Maybe(None) => false,
+        _ => panic!("bug: invalid argument sent to case function. Check constraints."),
+    }
+}
+
+// Higher order functions push a frame that runs their quotations directly on the
+// stack, one iteration at a time.
+
+/// Returns a frame, or pushes the error that prevented it.
+fn iterate(stack: &mut Stack<Parsed>, frame: Result<Frame, StackError>) -> Option<Frame> {
+    match frame {
+        Ok(frame) => Some(frame),
+        Err(err) => {
+            stack.push(Parsed::Error(err));
+            None
+        },
+    }
+}
+
+/// Returns a value as a quotation to run. Quotations run in the scope they were
+/// written in, and other values are wrapped in a quotation run in the current scope.
+pub(crate) fn as_code(value: Parsed, env: &Env) -> Parsed {
+    match value {
+        Parsed::Quotation(..) => value,
+        other => match other.coerce(&Type::Quotation) {
+            Parsed::Quotation(code, _) => Parsed::Quotation(code, env.scope.clone()),
+            other => other,
+        },
+    }
+}
+
+/// Returns the code a modifier or function runs, see `as_code`.
+pub(crate) fn body_of(modifier: Parsed, env: &Env) -> Result<Body<Rc<VecDeque<Parsed>>>, StackError> {
+    match as_code(modifier, env) {
+        Parsed::Quotation(code, scope) => Ok(Body { code, scope }),
+        Parsed::Error(err) => Err(err),
+        _ => Err(StackError::InvalidCoercion),
+    }
+}
+
+fn times_frame(count: Parsed, mods: Args, token: &Parsed, env: &Env) -> Result<Frame, StackError> {
+    let Args::Unary(modifier) = mods else {
+        panic!("bug: invalid closure count sent to times function")
+    };
+    Ok(Frame::Iteration(Iteration::times(count, body_of(modifier, env)?, token)?))
+}
+
+fn map_frame(list: Parsed, mods: Args, token: &Parsed, env: &Env) -> Result<Frame, StackError> {
+    let Args::Unary(modifier) = mods else {
+        panic!("bug: invalid closure count sent to map function")
+    };
+    Ok(Frame::Iteration(Iteration::map(list, body_of(modifier, env)?, token)))
+}
+
+fn each_frame(list: Parsed, mods: Args, token: &Parsed, env: &Env) -> Result<Frame, StackError> {
+    let Args::Unary(modifier) = mods else {
+        panic!("bug: invalid closure count sent to each function")
+    };
+    Ok(Frame::Iteration(Iteration::each(list, body_of(modifier, env)?, token)))
+}
+
+fn loop_frame(mods: Args, token: &Parsed, env: &Env) -> Result<Frame, StackError> {
+    let Args::Binary(condition, body) = mods else {
+        panic!("bug: invalid closure count sent to loop function")
+    };
+    Ok(Frame::Iteration(Iteration::until(body_of(condition, env)?, body_of(body, env)?, token)))
+}
+
+fn case_frame(value: Parsed, mods: Args, stack: &mut Stack<Parsed>, env: &Env) -> Result<Frame, StackError> {
+    let Args::Binary(success, otherwise) = mods else {
+        panic!("bug: invalid closure count sent to case function")
+    };
+    let branch = if open_case(value, stack) { success } else { otherwise };
+    Ok(Frame::new(body_of(branch, env)?))
+}
+
+fn guard_frame(op: &Op, mods: Args, token: &Parsed, env: &Env) -> Result<Frame, StackError> {
+    let Args::Binary(body, handler) = mods else {
+        panic!("bug: invalid closure count sent to {} function", op)
+    };
+    let (body, handler) = (body_of(body, env)?, body_of(handler, env)?);
+    Ok(Frame::Iteration(match op {
+        Op::Try => Iteration::try_catch(body, handler, token),
+        _ => Iteration::ensure(body, handler, token),
+    }))
+}
+
+fn global_frame(mods: Args, env: &Env) -> Result<Frame, StackError> {
+    let Args::Unary(modifier) = mods else {
+        panic!("bug: invalid closure count sent to global function")
+    };
+    Ok(Frame::global(body_of(modifier, env)?.code))
 }
 
 
 
-fn get_modifiers(op: &Op, input: &mut VecDeque<Parsed>, bindings: &mut HashMap<String, Binding>)
+fn get_modifiers(op: &Op, input: &VecDeque<Parsed>, pc: &mut usize, env: &mut Env)
     -> Result<Args, StackError> {
     let expected = op.get_signature().modifiers;
 
@@ -136,9 +831,10 @@ fn get_modifiers(op: &Op, input: &mut VecDeque<Parsed>, bindings: &mut HashMap<S
     let mut mod2 = Parsed::Error(StackError::Undefined);
     let mods;
     if let Params::Unary(_) | Params::Binary(_,_) = expected {
-        if let Some( m) = input.pop_front() {
-            mod1 = if op.clone() != Op::AsSymbol {
-                resolve_symbol(m, bindings)
+        if let Some(m) = input.get(*pc).cloned() {
+            *pc += 1;
+            mod1 = if !op.quotes_modifier() {
+                resolve(m, env)
             } else {
                 m
             }
@@ -147,9 +843,10 @@ fn get_modifiers(op: &Op, input: &mut VecDeque<Parsed>, bindings: &mut HashMap<S
         }
     }
     if let Params::Binary(_, _) = expected {
-        if let Some( m) = input.pop_front() {
-            mod2 = if op.clone() != Op::AsSymbol {
-                resolve_symbol(m, bindings)
+        if let Some(m) = input.get(*pc).cloned() {
+            *pc += 1;
+            mod2 = if !op.quotes_modifier() {
+                resolve(m, env)
             } else {
                 m
             }
@@ -163,7 +860,7 @@ fn get_modifiers(op: &Op, input: &mut VecDeque<Parsed>, bindings: &mut HashMap<S
             if constraint.is_satisfied_by(&mod1.get_type()) {
                 mods = Ok(Args::Unary(mod1));
             } else {
-                return Err(arg_mismatch(&op, &Args::Unary(mod1), false));
+                return Err(arg_mismatch(op, &Args::Unary(mod1), false));
             }
         },
         Params::Binary(c1, c2) => {
@@ -171,7 +868,7 @@ fn get_modifiers(op: &Op, input: &mut VecDeque<Parsed>, bindings: &mut HashMap<S
                 c2.is_satisfied_by(&mod2.get_type()) {
                 mods = Ok(Args::Binary(mod1, mod2));
             } else {
-                return Err(arg_mismatch(&op, &Args::Binary(mod1, mod2), false));
+                return Err(arg_mismatch(op, &Args::Binary(mod1, mod2), false));
             }
         },
         _ => panic!("closure arguments defined for max 2 quotations")
@@ -181,15 +878,18 @@ fn get_modifiers(op: &Op, input: &mut VecDeque<Parsed>, bindings: &mut HashMap<S
 
 
 
-fn resolve_symbol(sym: Parsed, bindings: &mut HashMap<String, Binding>) -> Parsed {
+/// Returns the value of a symbol, or the symbol itself if it is unbound. Quotations
+/// written in the code are given the scope of the code running.
+fn resolve(sym: Parsed, env: &mut Env) -> Parsed {
     match sym {
-        Parsed::Symbol(s) => {
-            if let Some (binding) = bindings.get(&s) {
-                binding.value.clone()
+        Parsed::Symbol(s, span) => {
+            if let Some (binding) = env.lookup(&s) {
+                binding.value
             } else {
-                Parsed::Symbol(s.clone())
+                Parsed::Symbol(s, span)
             }
         },
+        Parsed::Quotation(q, None) => Parsed::Quotation(q, env.scope.clone()),
         _ => sym
     }
 }
@@ -0,0 +1,387 @@
+/////////////////////////// LEXER /////////////////////////////////////////////////////////////////
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use crate::parse_error::ParseError;
+use crate::span::{Source, Span};
+
+#[derive(Clone, Debug, PartialEq)]
+/// The kinds of tokens produced by the lexer. Words are everything delimited by
+/// whitespace, such as numbers, brackets, operators and symbols. Comments are only
+/// produced when asked for, see `tokenize_with_comments`. Invalid tokens mark
+/// problems found by the lexer, and are reported by the parser.
+pub enum TokenKind {
+    Word,
+    String,
+    Comment,
+    Invalid(ParseError),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A lexical token along with the position it starts at. For string tokens,
+/// `text` holds the contents of the literal with escape sequences resolved, and
+/// for comments it holds the comment as written.
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+    /// Line breaks between the end of the previous token and the start of this one.
+    pub line_breaks: usize,
+}
+
+impl Token {
+    /// Creates a word token.
+    pub fn new(text: &str, span: Span) -> Token {
+        Token { kind: TokenKind::Word, text: text.to_string(), span, line_breaks: 0 }
+    }
+}
+
+/// Splits a string into tokens. The tokens are attributed to an unnamed source,
+/// see `tokenize` for reading named files.
+///
+/// # Examples
+///
+/// ```
+/// use bprog::lexer::to_tokens;
+///
+/// let words = to_tokens("one \"two  three\" four").into_iter()
+///     .map(|t| t.text)
+///     .collect::<Vec<String>>();
+///
+/// assert_eq!(vec!["one", "two  three", "four"], words);
+///
+/// ```
+///
+pub fn to_tokens(input: &str) -> VecDeque<Token> {
+    tokenize(&Source::new("<input>", input))
+}
+
+/// Splits the text of a source into word and string tokens, recording the line
+/// and column each token starts at.
+///
+/// Words are delimited by whitespace. A `"` at the start of a token opens a string
+/// literal, which runs to the next unescaped `"` and may span several lines. To keep
+/// the spaced `" text "` syntax working, when there is whitespace directly inside both
+/// quotes a single character of it at each end is treated as a separator and dropped;
+/// everything else is kept exactly.
+/// String literals support the escapes `\n`, `\t`, `\r`, `\0`, `\\`, `\"`, `\u{..}`,
+/// and a backslash at the end of a line, which skips the line break and the
+/// indentation of the next line.
+///
+/// Raw strings are written `r"..."`, or `r#"..."#` with any number of `#` when the
+/// contents contain quotes. Their contents are taken verbatim.
+///
+/// A token starting with `--` begins a comment running to the end of the line, and a
+/// token starting with `{-` begins a block comment ending at the matching `-}`. Block
+/// comments nest. Comments are dropped, while comment markers inside string literals
+/// remain part of the string.
+///
+/// Unterminated strings, block comments and invalid escape sequences are returned
+/// as invalid tokens, so the parser can report them along with any other problems.
+///
+/// # Examples
+///
+/// ```
+/// use bprog::span::Source;
+/// use bprog::lexer::{tokenize, TokenKind};
+///
+/// let tokens = tokenize(&Source::new("main.bprog", "1 \"a\\tb\"\n  r#\"say \"hi\"\"#"));
+///
+/// assert_eq!("a\tb", tokens[1].text);
+/// assert_eq!("say \"hi\"", tokens[2].text);
+/// assert_eq!(TokenKind::String, tokens[2].kind);
+/// assert_eq!("main.bprog:2:3", tokens[2].span.to_string());
+/// ```
+pub fn tokenize(source: &Rc<Source>) -> VecDeque<Token> {
+    Lexer::new(source, false).collect()
+}
+
+/// Splits the text of a source into tokens like `tokenize`, but keeps comments as
+/// comment tokens, for tools that need to reproduce them.
+///
+/// # Examples
+///
+/// ```
+/// use bprog::span::Source;
+/// use bprog::lexer::{tokenize_with_comments, TokenKind};
+///
+/// let tokens = tokenize_with_comments(&Source::new("main.bprog", "1 -- one\n{- two -} 2"));
+///
+/// assert_eq!(TokenKind::Comment, tokens[1].kind);
+/// assert_eq!("-- one", tokens[1].text);
+/// assert_eq!("{- two -}", tokens[2].text);
+/// assert_eq!(1, tokens[2].line_breaks);
+/// ```
+pub fn tokenize_with_comments(source: &Rc<Source>) -> VecDeque<Token> {
+    Lexer::new(source, true).collect()
+}
+
+
+/// Character cursor over a source, keeping track of the current line and column.
+/// Problems found along the way are queued as invalid tokens.
+struct Lexer<'a> {
+    source: &'a Rc<Source>,
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    col: usize,
+    // Line the previously produced token ended on.
+    end_line: usize,
+    keep_comments: bool,
+    invalid: VecDeque<Token>,
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token()
+    }
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a Rc<Source>, keep_comments: bool) -> Lexer<'a> {
+        Lexer {
+            source,
+            chars: source.text.chars().collect(),
+            pos: 0,
+            line: 1,
+            col: 1,
+            end_line: 1,
+            keep_comments,
+            invalid: VecDeque::new(),
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    fn span(&self) -> Span {
+        Span::new(self.source, self.line, self.col)
+    }
+
+    fn starts_with(&self, pattern: &str) -> bool {
+        pattern.chars().enumerate().all(|(i, c)| self.peek(i) == Some(c))
+    }
+
+    fn error(&mut self, error: ParseError) {
+        let span = self.span();
+        self.invalid.push_back(Token { kind: TokenKind::Invalid(error), text: String::new(), span, line_breaks: 0 });
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek(0) {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        loop {
+            if let Some(token) = self.invalid.pop_front() {
+                return Some(token);
+            }
+            self.skip_whitespace();
+            let span = self.span();
+            let (kind, text) = if self.starts_with("--") {
+                (TokenKind::Comment, self.line_comment())
+            } else if self.starts_with("{-") {
+                match self.block_comment() {
+                    Some(text) => (TokenKind::Comment, text),
+                    None => continue,
+                }
+            } else {
+                match self.peek(0) {
+                    Some('"') => self.string(&span),
+                    Some('r') if self.raw_string_hashes().is_some() => self.raw_string(&span),
+                    Some(_) => self.word(),
+                    None => return self.invalid.pop_front(),
+                }
+            };
+            if kind == TokenKind::Comment && !self.keep_comments {
+                continue;
+            }
+            let line_breaks = span.line - self.end_line;
+            self.end_line = self.line;
+            return Some(Token { kind, text, span, line_breaks });
+        }
+    }
+
+    /// Reads a `--` comment up to the end of the line.
+    fn line_comment(&mut self) -> String {
+        let mut text = String::new();
+        while let Some(c) = self.peek(0) {
+            if c == '\n' {
+                break;
+            }
+            text.push(c);
+            self.bump();
+        }
+        text.trim_end().to_string()
+    }
+
+    /// Reads a `{- -}` comment, including any comments nested inside it. Returns
+    /// None if the comment is never closed.
+    fn block_comment(&mut self) -> Option<String> {
+        let span = self.span();
+        let start = self.pos;
+        let mut depth = 0;
+        loop {
+            if self.starts_with("{-") {
+                depth += 1;
+                self.bump();
+            } else if self.starts_with("-}") {
+                depth -= 1;
+                self.bump();
+                if depth == 0 {
+                    self.bump();
+                    return Some(self.chars[start..self.pos].iter().collect());
+                }
+            } else if self.peek(0).is_none() {
+                self.error(ParseError::UnterminatedComment(span));
+                return None;
+            }
+            self.bump();
+        }
+    }
+
+    fn word(&mut self) -> (TokenKind, String) {
+        let mut text = String::new();
+        while let Some(c) = self.peek(0) {
+            if c.is_whitespace() {
+                break;
+            }
+            text.push(c);
+            self.bump();
+        }
+        (TokenKind::Word, text)
+    }
+
+    /// Returns the number of `#` between an `r` and a `"` at the cursor, if the
+    /// cursor is at the start of a raw string.
+    fn raw_string_hashes(&self) -> Option<usize> {
+        let mut hashes = 0;
+        while self.peek(1 + hashes) == Some('#') {
+            hashes += 1;
+        }
+        if self.peek(1 + hashes) == Some('"') { Some(hashes) } else { None }
+    }
+
+    fn raw_string(&mut self, span: &Span) -> (TokenKind, String) {
+        let hashes = self.raw_string_hashes().unwrap_or(0);
+        for _ in 0..hashes + 2 {
+            self.bump();
+        }
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                Some('"') if (0..hashes).all(|i| self.peek(i) == Some('#')) => {
+                    for _ in 0..hashes {
+                        self.bump();
+                    }
+                    return (TokenKind::String, text);
+                },
+                Some(c) => text.push(c),
+                None => return unterminated(span),
+            }
+        }
+    }
+
+    fn string(&mut self, span: &Span) -> (TokenKind, String) {
+        self.bump();
+        let mut text = String::new();
+        let mut escaped_head = false;
+        let mut escaped_tail = 0;
+        loop {
+            let escape_span = self.span();
+            match self.bump() {
+                Some('"') => break,
+                Some('\\') => {
+                    escaped_head |= text.is_empty();
+                    match self.bump() {
+                        Some('n') => text.push('\n'),
+                        Some('t') => text.push('\t'),
+                        Some('r') => text.push('\r'),
+                        Some('0') => text.push('\0'),
+                        Some('\\') => text.push('\\'),
+                        Some('"') => text.push('"'),
+                        Some('u') => match self.unicode_escape() {
+                            Ok(c) => text.push(c),
+                            Err(escape) => self.error(ParseError::InvalidEscape(escape, escape_span)),
+                        },
+                        Some('\n') => {
+                            escaped_head &= !text.is_empty();
+                            self.skip_whitespace();
+                            continue;
+                        },
+                        Some(c) => self.error(ParseError::InvalidEscape(format!("\\{}", c), escape_span)),
+                        None => return unterminated(span),
+                    }
+                    escaped_tail = text.len();
+                },
+                Some(c) => text.push(c),
+                None => return unterminated(span),
+            }
+        }
+        // A single whitespace character inside both quotes separates the quotes from
+        // the contents, as in `" text "`. Strings spaced on one side only are kept as
+        // written, and escaped characters are never dropped.
+        let spaced_head = text.starts_with(char::is_whitespace) && !escaped_head;
+        let spaced_tail = text.ends_with(char::is_whitespace) && text.len() > escaped_tail;
+        if spaced_head && spaced_tail {
+            text.pop();
+            if !text.is_empty() {
+                text.remove(0);
+            }
+        }
+        (TokenKind::String, text)
+    }
+
+    /// Reads the `{..}` part of a `\u{..}` escape, returning the escape as written
+    /// if it is not a valid code point of 1 to 6 hex digits.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let mut escape = "\\u".to_string();
+        if self.peek(0) != Some('{') {
+            return Err(escape);
+        }
+        escape.push('{');
+        self.bump();
+        let mut digits = String::new();
+        while let Some(c) = self.peek(0) {
+            if c == '}' {
+                escape.push('}');
+                self.bump();
+                return u32::from_str_radix(&digits, 16).ok()
+                    .filter(|_| !digits.is_empty() && digits.len() <= 6)
+                    .and_then(char::from_u32)
+                    .ok_or(escape);
+            }
+            if !c.is_ascii_hexdigit() {
+                break;
+            }
+            digits.push(c);
+            escape.push(c);
+            self.bump();
+        }
+        Err(escape)
+    }
+}
+
+fn unterminated(span: &Span) -> (TokenKind, String) {
+    (TokenKind::Invalid(ParseError::UnterminatedString(span.clone())), String::new())
+}
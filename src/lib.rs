@@ -1,9 +1,14 @@
-use crate::interpreter::{Binding, run};
+// The code base favours explicit returns and exhaustive type class tables.
+#![allow(clippy::needless_return, clippy::match_like_matches_macro)]
+
+use crate::interpreter::{Env, OnError};
 use crate::parsed::Parsed;
 use crate::parsing::parse;
 use crate::stack::Stack;
-use crate::utility::to_tokens;
-use std::collections::{HashMap, VecDeque};
+use crate::lexer::to_tokens;
+use std::collections::VecDeque;
+use std::panic;
+use std::path::PathBuf;
 
 // integration testing
 pub mod stack;
@@ -15,6 +20,18 @@ pub mod stack_error;
 pub mod op;
 pub mod interpreter;
 pub mod types;
+pub mod span;
+pub mod lexer;
+pub mod parse_error;
+pub mod formatter;
+pub mod random;
+pub mod name;
+pub mod compiler;
+pub mod vm;
+pub mod scope;
+pub mod module;
+pub mod package;
+pub mod debugger;
 
 pub fn t(input: &str) -> String {
     // Warning: don't move this function to another module, as integration tests in
@@ -26,10 +43,60 @@ pub fn t(input: &str) -> String {
     // 2. invoke interpreter with tokens from parser as input
     // 3. transform the result to a string (tip: implement Display traits)
 
+    // Every test runs on both the VM and the reference interpreter, which must agree
+    // on the result, or both fail.
+    both(input, |run| eval(input, Env::new(), run))
+}
+
+/// Runs the input like `t`, with the directories given searched for modules and packages.
+pub fn t_search(input: &str, search_path: &[&str]) -> String {
+    both(input, |run| {
+        let mut env = Env::new();
+        env.modules.search_path.extend(search_path.iter().map(PathBuf::from));
+        eval(input, env, run)
+    })
+}
+
+/// Runs the inputs one after the other on both runners, like the REPL does, and returns
+/// the stack left by the last of them.
+pub fn t_session(inputs: &[&str], on_error: OnError) -> String {
+    both(&inputs.join("\" then \""), |run| {
+        let mut stack: Stack<Parsed> = Stack::new();
+        let mut env = Env::new();
+        env.on_error = on_error;
+        for input in inputs {
+            match parse(&mut to_tokens(input)) {
+                Ok(parsed) => run(&mut stack, &mut VecDeque::from(parsed), &mut env, false),
+                Err(err) => return err.to_string(),
+            };
+        }
+        stack.contents_to_string()
+    })
+}
+
+type Runner = fn(&mut Stack<Parsed>, &mut VecDeque<Parsed>, &mut Env, bool) -> bool;
+
+/// Evaluates with the VM and the reference interpreter, checking that they agree.
+fn both(input: &str, eval: impl Fn(Runner) -> String + panic::RefUnwindSafe) -> String {
+    let compiled = panic::catch_unwind(|| eval(vm::run));
+    let reference = panic::catch_unwind(|| eval(interpreter::run));
+    match (compiled, reference) {
+        (Ok(compiled), Ok(reference)) => {
+            assert_eq!(compiled, reference, "the VM and the interpreter disagree on \"{}\"", input);
+            compiled
+        },
+        (Err(cause), Err(_)) => panic::resume_unwind(cause),
+        (Ok(compiled), Err(_)) => panic!("the interpreter failed on \"{}\", but the VM gave {}", input, compiled),
+        (Err(_), Ok(reference)) => panic!("the VM failed on \"{}\", but the interpreter gave {}", input, reference),
+    }
+}
+
+fn eval(input: &str, mut env: Env, run: Runner) -> String {
     let mut stack: Stack<Parsed> = Stack::new();
-    let mut dictionary: HashMap<String, Binding> = HashMap::new();
-    let parsed = parse(&mut VecDeque::from(to_tokens(&mut input.to_string())));
-    run(&mut stack, &mut VecDeque::from(parsed), &mut dictionary, true);
+    let parsed = match parse(&mut to_tokens(input)) {
+        Ok(parsed) => parsed,
+        Err(err) => return err.to_string(),
+    };
+    run(&mut stack, &mut VecDeque::from(parsed), &mut env, true);
     format!("{}", stack.top().unwrap())
-
-}
\ No newline at end of file
+}
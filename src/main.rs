@@ -1,14 +1,23 @@
 extern crate core;
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashSet, VecDeque};
+use std::fs;
 use std::fs::File;
 use std::{env, io};
 use std::io::Write;
-use bprog::interpreter::{Binding, run};
+use std::path::PathBuf;
+use bprog::debugger::Debugger;
+use bprog::formatter::format_source;
+use bprog::interpreter::{self, Env, OnError};
+use bprog::module::Modules;
+use bprog::package::{env_search_path, Package};
 use bprog::parsed::Parsed;
-use bprog::parsing::{parse};
+use bprog::parsing::{is_incomplete, parse};
 use bprog::stack::Stack;
-use bprog::utility::{get_input, to_tokens};
+use bprog::lexer::{tokenize, TokenKind};
+use bprog::span::Source;
+use bprog::utility::get_input;
+use bprog::vm;
 
 fn print_help() {
     println!(
@@ -16,15 +25,119 @@ fn print_help() {
         \t-r,  --repl-mode      Runs bprog in repl mode, allowing repeated input and print\n\
         \t                      of stack post input execution.\n\
         \t--src=\"<filename\">    Runs the contents of the specified file.\n\
+        \t--fmt=\"<filename\">    Prints the contents of the specified file in canonical layout.\n\
+        \t--lib=\"<directory>\"   Adds a directory to search for imported modules and packages in,\n\
+        \t                      before those of BPROG_PATH. Can be given more than once.\n\
+        \t--packages           Lists the packages installed in the search path.\n\
+        \t--which=\"<module>\"    Shows the file an import of the module from the current directory\n\
+        \t                      resolves to.\n\
+        \t--clear-on-error     In repl mode, replaces the stack by the failing token when an input\n\
+        \t                      fails, instead of rolling the stack and bindings back.\n\
+        \t-w,  --write          Used with --fmt, rewrites the file instead of printing it.\n\
         \t-h,  --help           Provides information about program arguments and use cases.\n\
         \t-i,  --info           Provides extended information about REPL mode usage.\n\
-        \t-dbg,--debug          Prints all program tokens before executing the program.\n"
+        \t-dbg,--debug          Prints all program tokens before executing the program.\n\
+        \t--reference           Runs programs on the reference interpreter instead of compiling\n\
+        \t                      them for the VM.\n\
+        \t--step               Runs programs in the debugger, on the reference interpreter.\n"
     )
 }
 
+/// Reads a REPL input, continuing with a continuation prompt for as long as the input
+/// leaves brackets, strings or block comments open. Typing :x on a continuation line
+/// discards the unfinished input.
+///
+/// # Returns
+///
+/// The complete input, or None if STDIN has been closed.
+fn read_repl_input() -> Option<String> {
+    let mut input = String::new();
+    loop {
+        print!("{}", if input.is_empty() { "bprog > " } else { "  ... > " });
+        io::stdout().flush().expect("failed to flush stdout");
+        match get_input(None) {
+            Ok(line) if !line.is_empty() => {
+                if !input.is_empty() && line.trim() == ":x" {
+                    println!("\tDiscarding unfinished input...");
+                    return Some(String::new());
+                }
+                input.push_str(&line);
+                if !is_incomplete(&input) {
+                    return Some(input);
+                }
+            },
+            _ => return None,
+        }
+    }
+}
+
+/// Formats a source file, printing the result or writing it back to the file.
+fn format_file(path: &str, write: bool) {
+    let program_text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => {
+            println!("no file with path \"{}\" found.", path);
+            return;
+        }
+    };
+    match format_source(&Source::new(path, &program_text)) {
+        Ok(formatted) if write => {
+            if formatted != program_text {
+                if let Err(err) = fs::write(path, formatted) {
+                    println!("failed to write \"{}\": {}", path, err);
+                }
+            }
+        },
+        Ok(formatted) => print!("{}", formatted),
+        Err(err) => println!("{}", err),
+    }
+}
+
+/// Prints the packages installed in the search path, marking those shadowed by a
+/// package of the same name earlier in the path.
+fn list_packages(search_path: &[PathBuf]) {
+    let packages = Package::installed(search_path);
+    if packages.is_empty() {
+        println!("no packages installed in the search path.");
+    }
+    let mut seen = HashSet::new();
+    packages.iter().for_each(|package| match package {
+        Ok(package) => {
+            let shadowed = if seen.insert(&package.name) { "" } else { " (shadowed)" };
+            let dependencies = match package.dependencies.is_empty() {
+                true => String::new(),
+                false => format!(" -> {}", package.dependencies.join(" ")),
+            };
+            println!("{} {}{}\t{}{}", package.name, package.version, dependencies, package.root.display(), shadowed);
+        },
+        Err(err) => println!("\x1b[31m{}\x1b[0m", err),
+    });
+}
+
+/// Prints the file a module resolves to, and the package it belongs to if any.
+fn which_module(module: &str, modules: &Modules) {
+    match modules.find(module, None) {
+        Ok(path) => match Package::containing(&path) {
+            Some(package) => println!("{}\t(package {} {})", path.display(), package.name, package.version),
+            None => println!("{}", path.display()),
+        },
+        Err(err) => println!("\x1b[31m{}\x1b[0m", err),
+    }
+}
+
+/// Constructs a debugger showing its stops on STDOUT and reading commands from STDIN.
+fn console_debugger() -> Debugger {
+    Debugger::new(Box::new(|text| {
+        println!("{}", text);
+        print!("debug > ");
+        io::stdout().flush().expect("failed to flush stdout");
+        get_input(None).ok().filter(|line| !line.is_empty())
+    }))
+}
+
 fn print_token_debug(token: &Parsed, depth: usize) {
     match token {
-        Parsed::Quotation(contents) => {
+        Parsed::Quotation(contents, _) => {
             let tab = "\t".repeat(depth);
             println!("\t{}Type:  \x1b[33mQuotation\x1b[0m \n\t{}Value:\n \n\t\t{}{{", tab, tab, tab);
             contents.iter().for_each(|t| print_token_debug(t, depth + 2));
@@ -39,16 +152,31 @@ fn print_token_debug(token: &Parsed, depth: usize) {
 fn main() {
     let mut use_repl_mode = false;
     let mut use_normal_mode = false;
+    let mut use_fmt_mode = false;
+    let mut write_formatted = false;
     let mut source_file = String::new();
     let mut use_help = false;
     let mut debug = false;
+    let mut reference = false;
+    let mut search_path: Vec<PathBuf> = Vec::new();
+    let mut use_packages_mode = false;
+    let mut which = None;
+    let mut on_error = OnError::default();
+    let mut step = false;
 
     let args: Vec<String> = env::args().collect::<Vec<String>>()[1..].to_vec();
-    let mut terminate_early = args.len() == 0;
+    let mut terminate_early = args.is_empty();
     args.iter().for_each(| arg | {
         if arg.starts_with("--src=") {
             source_file = arg.trim_start_matches("--src=").to_string();
             use_normal_mode = true;
+        } else if arg.starts_with("--fmt=") {
+            source_file = arg.trim_start_matches("--fmt=").to_string();
+            use_fmt_mode = true;
+        } else if arg.starts_with("--which=") {
+            which = Some(arg.trim_start_matches("--which=").to_string());
+        } else if arg.starts_with("--lib=") {
+            search_path.push(PathBuf::from(arg.trim_start_matches("--lib=")));
         } else {
             match arg.as_str() {
                 "-r" | "--repl-mode" => {
@@ -61,6 +189,21 @@ fn main() {
                 "-dbg" | "--debug" =>  {
                     debug = true;
                 }
+                "-w" | "--write" => {
+                    write_formatted = true;
+                }
+                "--reference" => {
+                    reference = true;
+                }
+                "--packages" => {
+                    use_packages_mode = true;
+                }
+                "--clear-on-error" => {
+                    on_error = OnError::Clear;
+                }
+                "--step" => {
+                    step = true;
+                }
                 _ => {
                     println!("Unrecognized arg <\x1b[31m{}\x1b[0m>.", arg);
                     terminate_early = true;
@@ -68,111 +211,157 @@ fn main() {
             };
         }
     } );
-    terminate_early = terminate_early || !(use_repl_mode ^ use_normal_mode);
-    match (use_repl_mode, use_normal_mode, use_help) {
-        (_,_, true) => {
+    let modes = [use_repl_mode, use_normal_mode, use_fmt_mode, use_packages_mode, which.is_some()].iter().filter(|m| **m).count();
+    terminate_early = terminate_early || modes != 1;
+    match (modes, use_help) {
+        (_, true) => {
             print_help()
         },
-        (true, true, _) => {
-            println!("Can only run either source file or repl mode. Use --help for more information");
-        },
-        (false, false, false) => {
+        (0, _) => {
             println!("No valid arguments provided. Use --help for more information");
         },
-        _ => {}
+        (1, _) => {},
+        _ => {
+            println!("Can only run one of source file, format, packages, which or repl mode. Use --help for more information");
+        },
     }
     if terminate_early { return }
 
+    if use_fmt_mode {
+        format_file(&source_file, write_formatted);
+        return;
+    }
 
+    search_path.extend(env_search_path());
+    if use_packages_mode {
+        list_packages(&search_path);
+        return;
+    }
+    let mut modules = Modules::default();
+    modules.search_path = search_path;
+    if let Some(module) = which {
+        which_module(&module, &modules);
+        return;
+    }
 
-
-
+    // Only the reference interpreter stops in the debugger.
+    let run = if reference || step { interpreter::run } else { vm::run };
     let mut stack: Stack<Parsed> = Stack::new();
-    let mut dictionary: HashMap<String, Binding> = HashMap::new();
+    let mut env = Env::new();
+    env.modules = modules;
+    env.on_error = on_error;
 
     let mut prelude = File::open("./prelude.bprog").unwrap();
-    if let Ok(mut pre_definitions) = get_input(Some(&mut prelude)) {
-        let mut run_tokens = VecDeque::from(parse(&mut to_tokens(&mut pre_definitions)));
-        run(&mut stack, &mut run_tokens, &mut dictionary, true);
-        println!("prelude definitions loaded!");
+    if let Ok(pre_definitions) = get_input(Some(&mut prelude)) {
+        let source = Source::new("prelude.bprog", &pre_definitions);
+        match parse(&mut tokenize(&source)) {
+            Ok(parsed) => {
+                run(&mut stack, &mut VecDeque::from(parsed), &mut env, true);
+                println!("prelude definitions loaded!");
+            },
+            Err(err) => println!("{}\nprelude definitions not loaded.", err),
+        }
+    }
+
+    if step {
+        env.debugger = Some(console_debugger());
     }
 
     if use_repl_mode {
         'repl: loop {
-            print!("bprog > ");
-            io::stdout().flush().expect("TODO: panic message");
-            match get_input(None) {
-                Ok(mut input_string) => {
-                    let mut tokens = to_tokens(&mut input_string);
-                    if let Some(first_element) = tokens.front() {
-                        if let Some(_) = match first_element.as_str() {
-                            ":dbg" => {
-                                debug = !debug;
-                                None
-                            },
-                            ":i" => {
-                                if let Some(parsed) = stack.top() {
-                                    print_token_debug(parsed, 1);
-                                }
-                                None
-                            },
-                            ":h" => {
-                                println!("repl operations:\n\
-                                \t:dbg - Toggles debug mode, showing details about every input token.\n\
-                                \t:i   - Shows type and value of the top stack value.\n\
-                                \t:h   - Shows repl operations.\n\
-                                \t:c   - Clears the stack of contents.\n\
-                                \t:q   - Ends REPL mode and exits bprog application.\n");
-                                None
-                            },
-                            ":q" => {
-                                println!("Exiting bprog");
-                                break 'repl;
-                            },
-                            ":c" => {
-                                println!("\tEmptying stack...");
-                                stack.clear();
-                                None
-                            }
-                            _ => Some(1)
-                        } {} else {
-                            tokens.clear();
+            let Some(input_string) = read_repl_input() else {
+                println!("\nExiting bprog");
+                break 'repl;
+            };
+            let mut tokens = tokenize(&Source::new("<repl>", &input_string));
+            if let Some(first_element) = tokens.front().filter(|t| t.kind == TokenKind::Word) {
+                if !match first_element.text.as_str() {
+                    ":dbg" => {
+                        debug = !debug;
+                        false
+                    },
+                    ":step" => {
+                        step = !step;
+                        env.debugger = step.then(console_debugger);
+                        println!("\tDebugger {}.", if step { "on" } else { "off" });
+                        false
+                    },
+                    ":i" => {
+                        if let Some(parsed) = stack.top() {
+                            print_token_debug(parsed, 1);
                         }
+                        false
+                    },
+                    ":h" => {
+                        println!("repl operations:\n\
+                        \t:dbg - Toggles debug mode, showing details about every input token.\n\
+                        \t:step - Toggles the debugger, stopping at every input token.\n\
+                        \t:i   - Shows type and value of the top stack value.\n\
+                        \t:h   - Shows repl operations.\n\
+                        \t:c   - Clears the stack of contents.\n\
+                        \t:q   - Ends REPL mode and exits bprog application.\n\
+                        \t:x   - Discards unfinished input spanning several lines.\n\
+                        \n\
+                        Input continues on the next line while {{, [, \" or {{- are left open.\n");
+                        false
+                    },
+                    ":q" => {
+                        println!("Exiting bprog");
+                        break 'repl;
+                    },
+                    ":c" => {
+                        println!("\tEmptying stack...");
+                        stack.clear();
+                        false
                     }
-                    let parsed_tokens = parse(&mut tokens);
+                    _ => true
+                } {
+                    tokens.clear();
+                }
+            }
+            match parse(&mut tokens) {
+                Ok(parsed_tokens) => {
                     if debug { parsed_tokens.iter().for_each(|t| print_token_debug(t, 0) )}
 
                     let mut run_tokens = VecDeque::from(parsed_tokens);
-                    run(&mut stack, &mut run_tokens, &mut dictionary , false);
-                    println!("stack > {}", stack.contents_to_string());
-                }
-                _ => {}
+                    if let Some(debugger) = &mut env.debugger {
+                        debugger.restart();
+                    }
+                    let run = if step { interpreter::run } else { run };
+                    run(&mut stack, &mut run_tokens, &mut env , false);
+                },
+                Err(err) => println!("{}", err),
             }
+            println!("stack > {}", stack.contents_to_string());
             println!();
         };
     } else {
         if let Ok(mut program_file) = File::open(&source_file) {
-            match get_input(Some(&mut program_file)) {
-                Ok(mut tokens) => {
-                    let stack_tokens = parse(&mut to_tokens(&mut tokens));
-                    if debug {
-                        println!("\ndebug mode: Displaying parsed input");
-                        stack_tokens.iter()
-                            .for_each( |t| print_token_debug(t, 0));
-                        println!()
-                    }
-                    let mut run_tokens = VecDeque::from(stack_tokens);
-                    println!("running...");
-                    run(&mut stack, &mut run_tokens, &mut dictionary, true );
-                    if stack.size() != 1 {
-                        println!("stack: {}", stack.contents_to_string());
-                        panic!("Program did not evaluate to a single value!")
-                    } else {
-                        println!("final stack value: {}", stack.top()
-                            .expect("guaranteed to contain one value."))
+            if let Ok(program_text) = get_input(Some(&mut program_file)) {
+                let source = Source::new(&source_file, &program_text);
+                let stack_tokens = match parse(&mut tokenize(&source)) {
+                    Ok(parsed) => parsed,
+                    Err(err) => {
+                        println!("{}", err);
+                        return;
                     }
+                };
+                if debug {
+                    println!("\ndebug mode: Displaying parsed input");
+                    stack_tokens.iter()
+                        .for_each( |t| print_token_debug(t, 0));
+                    println!()
+                }
+                let mut run_tokens = VecDeque::from(stack_tokens);
+                println!("running...");
+                run(&mut stack, &mut run_tokens, &mut env, true );
+                if stack.size() != 1 {
+                    println!("stack: {}", stack.contents_to_string());
+                    panic!("Program did not evaluate to a single value!")
+                } else {
+                    println!("final stack value: {}", stack.top()
+                        .expect("guaranteed to contain one value."))
                 }
-                _ => {}
             }
         } else {
             println!("no file with path \"{}\" found.", source_file);
@@ -0,0 +1,157 @@
+/////////////////////////// MODULE ////////////////////////////////////////////////////////////////
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::interpreter::{Args, Env};
+use crate::lexer::tokenize;
+use crate::name::Name;
+use crate::op::Op;
+use crate::package::{is_below, Package};
+use crate::parsed::Parsed;
+use crate::parsing::parse;
+use crate::scope::Scope;
+use crate::span::{Source, Span};
+use crate::stack::Stack;
+use crate::stack_error::StackError;
+
+/// Runs the code of a module in the given scope on a stack of its own, reporting any
+/// error like `interpreter::run`. Returns whether all of the code ran.
+pub(crate) type Runner = fn(&mut Stack<Parsed>, VecDeque<Parsed>, Option<Rc<Scope>>, &mut Env, bool) -> bool;
+
+/// The modules imported by a program. A module is a `.bprog` file whose top level runs
+/// in a scope of its own the first time it is imported, and whose definitions are then
+/// bound by every import of it.
+#[derive(Default)]
+pub struct Modules {
+    /// Directories searched for modules not found next to the file importing them, and
+    /// for installed packages.
+    pub search_path: Vec<PathBuf>,
+    /// Scopes of the modules loaded so far, by the canonical path of their file.
+    loaded: HashMap<PathBuf, Rc<Scope>>,
+    /// Modules being loaded, each imported by the one before it.
+    loading: Vec<PathBuf>,
+}
+
+impl Modules {
+    /// Finds the file of a module named by a path without its `.bprog` extension.
+    /// Modules are looked for next to the file importing them, then in the packages
+    /// installed in the search path, where `name/path` is the module `path` of the
+    /// package `name`, and last in each directory of the search path.
+    ///
+    /// Modules of a package may only import the packages it depends on and modules in
+    /// its own directory, and a package is only used when all of its dependencies are
+    /// installed. Modules found in the search path may not be named by paths leaving it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bprog::module::Modules;
+    ///
+    /// let mut modules = Modules::default();
+    /// modules.search_path.push("tests/modules".into());
+    /// modules.search_path.push("tests/packages".into());
+    ///
+    /// assert!(modules.find("geometry", None).unwrap().ends_with("tests/modules/geometry.bprog"));
+    /// assert!(modules.find("shapes/circle", None).unwrap().ends_with("tests/packages/shapes/circle.bprog"));
+    /// assert!(modules.find("missing", None).is_err());
+    /// ```
+    pub fn find(&self, module: &str, importer: Option<&Span>) -> Result<PathBuf, String> {
+        let file = format!("{}.bprog", module);
+        let importing = importer.and_then(|span| Package::containing(Path::new(span.file())));
+        let beside = importer
+            .and_then(|span| Path::new(span.file()).parent())
+            .unwrap_or(Path::new(""))
+            .join(&file);
+        let (name, path) = module.split_once('/').unwrap_or((module, ""));
+        let path = if beside.is_file() {
+            match &importing {
+                Some(importing) if !importing.holds(&beside) => {
+                    return Err(format!("module {} is outside of package {}", module, importing.name));
+                },
+                _ => beside,
+            }
+        } else if !is_below(module) {
+            return Err(format!("module path {} leaves the directories searched", module));
+        } else if let Some(package) = Package::find(name, &self.search_path) {
+            self.find_in_package(package?, path, importing)?
+        } else {
+            self.search_path.iter()
+                .map(|dir| dir.join(&file))
+                .find(|path| path.is_file())
+                .ok_or_else(|| format!("no module {} found", module))?
+        };
+        path.canonicalize().map_err(|err| format!("failed to open {}: {}", path.display(), err))
+    }
+
+    fn find_in_package(&self, package: Package, path: &str, importing: Option<Package>) -> Result<PathBuf, String> {
+        if let Some(importing) = importing.filter(|importing| importing.name != package.name) {
+            if !importing.dependencies.contains(&package.name) {
+                return Err(format!("package {} does not depend on {}", importing.name, package.name));
+            }
+        }
+        if let Some(missing) = package.dependencies.iter().find(|dep| Package::find(dep, &self.search_path).is_none()) {
+            return Err(format!("package {} depends on {}, which is not installed", package.name, missing));
+        }
+        let file = package.module(path)?;
+        match file.is_file() {
+            true => Ok(file),
+            false => Err(format!("package {} has no module {}", package.name, file.display())),
+        }
+    }
+}
+
+/// Imports the module named by the modifier of `import` or `use`, loading it unless
+/// it has been already. Each definition of the module is bound in the current scope
+/// under its name qualified by the module's, as in `geometry.area`, and `use` binds it
+/// under its own name as well.
+pub(crate) fn import(op: &Op, mods: Args, token: &Parsed, env: &mut Env, run: Runner, fatal: bool) -> Parsed {
+    let Args::Unary(Parsed::Symbol(module, _)) = mods else {
+        panic!("bug: invalid modifier sent to {}. Check constraints.", op)
+    };
+    let scope = match load(&module, token.span(), env, run, fatal) {
+        Ok(scope) => scope,
+        Err(err) => return Parsed::Error(err),
+    };
+    let prefix = Path::new(module.as_str()).file_name()
+        .map_or(module.to_string(), |name| name.to_string_lossy().to_string());
+    for (name, binding) in scope.bindings() {
+        env.bind(&Name::new(&format!("{}.{}", prefix, name)), binding.clone());
+        if op == &Op::Use {
+            env.bind(&name, binding);
+        }
+    }
+    Parsed::Void
+}
+
+/// Returns the scope of a module, running its file first if it has not been loaded.
+fn load(module: &str, importer: Option<&Span>, env: &mut Env, run: Runner, fatal: bool) -> Result<Rc<Scope>, StackError> {
+    let path = env.modules.find(module, importer).map_err(StackError::Module)?;
+    if let Some(scope) = env.modules.loaded.get(&path) {
+        return Ok(Rc::clone(scope));
+    }
+    if let Some(start) = env.modules.loading.iter().position(|loading| loading == &path) {
+        let chain = env.modules.loading[start..].iter()
+            .chain(std::iter::once(&path))
+            .map(|path| path.display().to_string())
+            .collect();
+        return Err(StackError::ImportCycle(chain));
+    }
+    let text = fs::read_to_string(&path)
+        .map_err(|err| StackError::Module(format!("failed to read {}: {}", path.display(), err)))?;
+    let code = parse(&mut tokenize(&Source::new(&path.display().to_string(), &text)))
+        .map_err(StackError::Parse)?;
+
+    let scope = Scope::module();
+    let importer_scope = env.scope.take();
+    env.modules.loading.push(path.clone());
+    let ran = run(&mut Stack::new(), VecDeque::from(code), Some(Rc::clone(&scope)), env, fatal);
+    env.modules.loading.pop();
+    env.scope = importer_scope;
+    if !ran {
+        return Err(StackError::Module(format!("module {} stopped by an error", module)));
+    }
+    env.modules.loaded.insert(path, Rc::clone(&scope));
+    Ok(scope)
+}
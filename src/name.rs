@@ -0,0 +1,91 @@
+/////////////////////////// NAME //////////////////////////////////////////////////////////////////
+
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
+
+thread_local! {
+    static NAMES: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+#[derive(Clone)]
+/// The interned name of a symbol. Each name is stored once, so copying a name is
+/// cheap and two names are compared by their address rather than their text.
+///
+/// # Examples
+///
+/// ```
+/// use bprog::name::Name;
+///
+/// let name = Name::new("square");
+///
+/// assert_eq!(Name::new("square"), name);
+/// assert_ne!(Name::new("cube"), name);
+/// assert_eq!("square", name.as_str());
+/// ```
+pub struct Name(Rc<str>);
+
+impl Name {
+    /// Returns the name with the given text, storing the text if it is new.
+    pub fn new(text: &str) -> Name {
+        NAMES.with(|names| {
+            let mut names = names.borrow_mut();
+            if let Some(name) = names.get(text) {
+                return Name(Rc::clone(name));
+            }
+            let name: Rc<str> = Rc::from(text);
+            names.insert(Rc::clone(&name));
+            Name(name)
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Name {}
+
+/// Hashes the text of the name, so that maps keyed by names can be searched by text.
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Debug for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
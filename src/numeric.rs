@@ -3,15 +3,26 @@
 use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::{Display, Formatter};
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
 use std::str::FromStr;
+use num::{BigInt, BigRational, FromPrimitive, Num, One, Signed, ToPrimitive, Zero};
+use num::integer::Integer;
 use crate::stack_error::StackError;
 
 #[derive(Clone, Debug)]
 /// Numeric encapsulates numeric types such as integers and floats, implementing
 /// basic arithmetic operations such as +, -, / and *.
+///
+/// Integers that do not fit in an i128 are held as BigInteger. Arithmetic on integers
+/// is exact, and results are moved between the two representations as needed, so
+/// an Integer always holds values that fit and a BigInteger values that do not.
+///
+/// Rationals are exact fractions, kept in lowest terms. Arithmetic results with a
+/// denominator of 1 become integers.
 pub enum Numeric {
     Integer(i128),
+    BigInteger(BigInt),
+    Rational(BigRational),
     Float(f64),
     NumError(StackError)
 }
@@ -21,7 +32,12 @@ impl Neg for Numeric {
 
     fn neg(self) -> Self::Output {
         match self {
-            Numeric::Integer(v) => Numeric::Integer(-v),
+            Numeric::Integer(v) => match v.checked_neg() {
+                Some(v) => Numeric::Integer(v),
+                None => Numeric::from_big(-BigInt::from(v)),
+            },
+            Numeric::BigInteger(v) => Numeric::from_big(-v),
+            Numeric::Rational(v) => Numeric::Rational(-v),
             Numeric::Float(v) => Numeric::Float(-v),
             Numeric::NumError(_) => self.clone()
         }
@@ -31,30 +47,101 @@ impl Neg for Numeric {
 // Numeric methods
 impl Numeric {
 
-    /// Allows implicit conversion from numeric to boolean by considering any
-    /// non zero values as true.
+    /// Creates an integer from a big integer, using Numeric::Integer if the value
+    /// fits in an i128.
+    ///
+    /// ```
+    /// use num::BigInt;
+    /// use bprog::numeric::Numeric;
+    ///
+    /// assert!(matches!(Numeric::from_big(BigInt::from(42)), Numeric::Integer(42)));
+    /// assert!(matches!(Numeric::from_big(BigInt::from(i128::MAX) * 2), Numeric::BigInteger(_)));
+    /// ```
+    pub fn from_big(val: BigInt) -> Numeric {
+        match val.to_i128() {
+            Some(v) => Numeric::Integer(v),
+            None => Numeric::BigInteger(val),
+        }
+    }
+
+    /// Creates a number from a fraction, using an integer if the denominator is 1.
+    ///
+    /// ```
+    /// use num::{BigInt, BigRational};
+    /// use bprog::numeric::Numeric;
     ///
-    /// # Safety
+    /// let half = BigRational::new(BigInt::from(2), BigInt::from(4));
+    /// assert_eq!("1/2", Numeric::from_ratio(half).to_string());
     ///
-    /// This function returns false for NumErrors, meaning its use gives no
-    /// indication whether self is a valid numeric representation or not.
-    fn is_true(&self) -> bool {
+    /// let two = BigRational::new(BigInt::from(4), BigInt::from(2));
+    /// assert!(matches!(Numeric::from_ratio(two), Numeric::Integer(2)));
+    /// ```
+    pub fn from_ratio(val: BigRational) -> Numeric {
+        if val.is_integer() {
+            Numeric::from_big(val.to_integer())
+        } else {
+            Numeric::Rational(val)
+        }
+    }
+
+    /// Returns the value as an exact fraction, if self is an integer or rational.
+    pub fn to_ratio(&self) -> Option<BigRational> {
         match self {
-            Numeric::Integer(val) => *val != 0,
-            Numeric::Float(val) => *val != 0.0,
-            Numeric::NumError(_) => false,
+            Numeric::Rational(v) => Some(v.clone()),
+            _ => self.to_big().map(BigRational::from_integer),
+        }
+    }
+
+    /// Returns the value as a big integer, if self is an integer.
+    pub fn to_big(&self) -> Option<BigInt> {
+        match self {
+            Numeric::Integer(v) => Some(BigInt::from(*v)),
+            Numeric::BigInteger(v) => Some(v.clone()),
+            _ => None
         }
     }
 
     /// Attempts to return self converted from any enum variant to Numeric::Int.
-    /// If the type cannot be converted to Int, it returns itself.
+    /// Floats are truncated towards zero. If the type cannot be converted to Int,
+    /// it returns itself.
     pub fn as_integer(& self) -> Numeric {
         match self {
-            Numeric::Float(val) => Numeric::Integer(*val as i128),
+            Numeric::Float(val) if val.abs() < i128::MAX as f64 => Numeric::Integer(*val as i128),
+            Numeric::Float(val) => match BigInt::from_f64(val.trunc()) {
+                Some(big) => Numeric::from_big(big),
+                None => Numeric::Integer(*val as i128),
+            },
+            Numeric::Rational(val) => Numeric::from_big(val.to_integer()),
             _ => self.clone()
         }
     }
 
+    /// Attempts to return self converted from any enum variant to Numeric::Rational.
+    /// Floats are converted exactly. If the type cannot be converted to Rational,
+    /// it returns itself.
+    pub fn as_rational(& self) -> Numeric {
+        match self {
+            Numeric::Float(val) => match BigRational::from_float(*val) {
+                Some(ratio) => Numeric::Rational(ratio),
+                None => Numeric::NumError(StackError::InvalidCoercion),
+            },
+            _ => match self.to_ratio() {
+                Some(ratio) => Numeric::Rational(ratio),
+                None => self.clone(),
+            }
+        }
+    }
+
+    /// Returns the numerator of an integer or rational, or None for other values.
+    pub fn numerator(&self) -> Option<Numeric> {
+        self.to_ratio().map(|ratio| Numeric::from_big(ratio.numer().clone()))
+    }
+
+    /// Returns the denominator of an integer or rational, or None for other values.
+    pub fn denominator(&self) -> Option<Numeric> {
+        self.to_ratio().map(|ratio| Numeric::from_big(ratio.denom().clone()))
+    }
+
     /// Attempts to return self converted from any enum variant to Numeric::Float.
     /// If the type cannot be converted to Int, it returns itself.
     ///
@@ -72,6 +159,8 @@ impl Numeric {
     pub fn as_float(& self) -> Numeric {
         match self {
             Numeric::Integer(val) => Numeric::Float(*val as f64),
+            Numeric::BigInteger(val) => Numeric::Float(val.to_f64().unwrap_or(f64::NAN)),
+            Numeric::Rational(val) => Numeric::Float(val.to_f64().unwrap_or(f64::NAN)),
             _ => self.clone()
         }
     }
@@ -83,11 +172,13 @@ impl Display for Numeric {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Numeric::Integer(v) => write!(f, "{}", v),
+            Numeric::BigInteger(v) => write!(f, "{}", v),
+            Numeric::Rational(v) => write!(f, "{}", v),
             Numeric::Float(v)=> {
                 if v.fract() == 0.0 {
-                    write!(f, "{}.0", v.to_string())
+                    write!(f, "{}.0", v)
                 } else {
-                    write!(f, "{}", v.to_string())
+                    write!(f, "{}", v)
                 }
             }
             Numeric::NumError(err) => write!(f, "{}", err)
@@ -95,14 +186,17 @@ impl Display for Numeric {
     }
 }
 
-/// Implements PartialOrd for Numeric. Relies on binary_numerical with
-/// the try_* lt, le, gt and ge functions for actual logic.
+/// Implements PartialOrd for Numeric. Integers and rationals are compared exactly,
+/// while comparisons involving floats convert both operands to float.
 ///
 /// Note that partial_cmp returns an Ordering enum only if the types match
 /// in a way that allows implicit conversion, such as int int or float int.
 impl PartialOrd for Numeric {
 
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if let (Some(v1), Some(v2)) = (self.to_ratio(), other.to_ratio()) {
+            return Some(v1.cmp(&v2));
+        }
         match (self.as_float(), other.as_float()) {
             (Numeric::Float(v1), Numeric::Float(v2)) => {
                 v1.partial_cmp(&v2)
@@ -110,22 +204,6 @@ impl PartialOrd for Numeric {
             (_, _) => None
         }
     }
-
-    fn lt(&self, other: &Self) -> bool {
-        binary_numerical(self, other, try_lt).is_true()
-    }
-
-    fn le(&self, other: &Self) -> bool {
-        binary_numerical(self, other, try_le).is_true()
-    }
-
-    fn gt(&self, other: &Self) -> bool {
-        binary_numerical(self, other, try_gt).is_true()
-    }
-
-    fn ge(&self, other: &Self) -> bool {
-        binary_numerical(self, other, try_ge).is_true()
-    }
 }
 
 /// Implements PartialEq for the Numeric type.
@@ -151,21 +229,21 @@ impl PartialEq for Numeric {
             (Numeric::Integer(v), Numeric::Integer(v2)) => {
                 v == v2
             }
-            (Numeric::Float(v), Numeric::Integer(v2)) => {
-                *v == *v2 as f64
-            },
-            (Numeric::Integer(v), Numeric::Float(v2)) => {
-                *v as f64 == *v2
+            (Numeric::Float(v), other) |
+            (other, Numeric::Float(v)) => {
+                matches!(other.as_float(), Numeric::Float(v2) if *v == v2)
             },
-            (Numeric::Float(v), Numeric::Float(v2)) => {
-                v == v2
+            _ => match (self.to_ratio(), other.to_ratio()) {
+                (Some(v), Some(v2)) => v == v2,
+                _ => false
             }
-            _ => false
         }
     }
 }
 
-/// Implements FromStr for Numeric for parsing directly from string.
+/// Implements FromStr for Numeric for parsing directly from string. Integer
+/// literals too large for an i128 are parsed as BigInteger, and fractions written
+/// as `<integer>/<positive integer>`, such as `-1/3`, are parsed as Rational.
 impl FromStr for Numeric {
     type Err = String;
 
@@ -173,6 +251,19 @@ impl FromStr for Numeric {
         if let Ok(val) = s.parse::<i128>() {
             return Ok(Numeric::Integer(val));
         }
+        if let Some(val) = parse_big(s).or_else(|| parse_radix(s)) {
+            return Ok(Numeric::from_big(val));
+        }
+        if let Some((numer, denom)) = s.split_once('/') {
+            // The denominator is written without a sign.
+            if denom.starts_with(|c: char| c.is_ascii_digit()) {
+                if let (Some(numer), Some(denom)) = (parse_big(numer), parse_big(denom)) {
+                    if !denom.is_zero() {
+                        return Ok(Numeric::from_ratio(BigRational::new(numer, denom)));
+                    }
+                }
+            }
+        }
         if let Ok(val) = s.parse::<f64>() {
             return Ok(Numeric::Float(val));
         }
@@ -180,79 +271,680 @@ impl FromStr for Numeric {
     }
 }
 
+/// Parses an integer written as decimal digits with an optional sign.
+fn parse_big(s: &str) -> Option<BigInt> {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    s.parse::<BigInt>().ok()
+}
+
+/// Parses an integer written in binary, octal or hexadecimal, with a `0b`, `0o` or `0x`
+/// prefix after an optional sign.
+fn parse_radix(s: &str) -> Option<BigInt> {
+    let (negative, rest) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let radix = match rest.get(..2)? {
+        "0b" | "0B" => 2,
+        "0o" | "0O" => 8,
+        "0x" | "0X" => 16,
+        _ => return None,
+    };
+    let digits = &rest[2..];
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let val = BigInt::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -val } else { val })
+}
 
 /// Implements addition for the Numeric type. Int x Float operations
 /// will result in Float variants being returned.
-impl<'a, 'b> Add<&'b Numeric> for &'a Numeric {
+impl<'b> Add<&'b Numeric> for &Numeric {
     type Output = Numeric;
     fn add(self, rhs: &'b Numeric) -> Self::Output {
-        binary_numerical(self, rhs, try_add)
+        self.arithmetic(Arithmetic::Add, rhs, OverflowPolicy::default())
     }
 }
 
 /// Implements subtraction for the Numeric type. Int x Float operations
 /// will result in Float variants being returned.
-impl<'a, 'b> Sub<&'b Numeric> for &'a Numeric {
+impl<'b> Sub<&'b Numeric> for &Numeric {
     type Output = Numeric;
     fn sub(self, rhs: &'b Numeric) -> Self::Output {
-        binary_numerical(self, rhs, try_sub)
+        self.arithmetic(Arithmetic::Sub, rhs, OverflowPolicy::default())
     }
 }
 
 /// Implements multiplication for the Numeric type. Int x Float operations
 /// will result in Float variants being returned.
-impl<'a, 'b> Mul<&'b Numeric> for &'a Numeric {
+impl<'b> Mul<&'b Numeric> for &Numeric {
     type Output = Numeric;
     fn mul(self, rhs: &'b Numeric) -> Self::Output {
-        binary_numerical(self, rhs, try_mul)
+        self.arithmetic(Arithmetic::Mul, rhs, OverflowPolicy::default())
     }
 }
 
-/// Implements division for the Numeric type. Int x Float operations
-/// will result in Float variants being returned.
-impl<'a, 'b> Div<&'b Numeric> for &'a Numeric {
+/// Implements division for the Numeric type. Int x Int division truncates
+/// towards zero, division involving a Rational is exact, while Int x Float
+/// operations will result in Float variants being returned.
+impl<'b> Div<&'b Numeric> for &Numeric {
     type Output = Numeric;
     fn div(self, rhs: &'b Numeric) -> Self::Output {
-        binary_numerical(self, rhs, try_div)
+        self.arithmetic(Arithmetic::Div, rhs, OverflowPolicy::default())
+    }
+}
+
+/// Implements remainder for the Numeric type. The result has the sign of the
+/// left hand operand.
+impl<'b> Rem<&'b Numeric> for &Numeric {
+    type Output = Numeric;
+    fn rem(self, rhs: &'b Numeric) -> Self::Output {
+        self.arithmetic(Arithmetic::Rem, rhs, OverflowPolicy::default())
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Ways of rounding a number to an integer. Round takes halves away from zero.
+pub enum Rounding {
+    Floor,
+    Ceil,
+    Round,
+    Truncate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Functions on real numbers, computed with floats.
+pub enum RealFunction {
+    Sqrt,
+    Exp,
+    Ln,
+    Log10,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+}
+
+impl RealFunction {
+    fn apply(&self, x: f64) -> f64 {
+        match self {
+            RealFunction::Sqrt => x.sqrt(),
+            RealFunction::Exp => x.exp(),
+            RealFunction::Ln => x.ln(),
+            RealFunction::Log10 => x.log10(),
+            RealFunction::Sin => x.sin(),
+            RealFunction::Cos => x.cos(),
+            RealFunction::Tan => x.tan(),
+            RealFunction::Asin => x.asin(),
+            RealFunction::Acos => x.acos(),
+            RealFunction::Atan => x.atan(),
+        }
+    }
+
+    /// Checks whether the function is defined for a value.
+    fn is_defined_for(&self, x: f64) -> bool {
+        match self {
+            RealFunction::Sqrt => x >= 0.0,
+            RealFunction::Ln | RealFunction::Log10 => x > 0.0,
+            RealFunction::Asin | RealFunction::Acos => (-1.0..=1.0).contains(&x),
+            RealFunction::Sin | RealFunction::Cos | RealFunction::Tan => x.is_finite(),
+            RealFunction::Exp | RealFunction::Atan => !x.is_nan(),
+        }
+    }
+}
+
+/// Implements Display for RealFunction, giving the names of the matching ops.
+impl Display for RealFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RealFunction::Sqrt => write!(f, "sqrt"),
+            RealFunction::Exp => write!(f, "exp"),
+            RealFunction::Ln => write!(f, "ln"),
+            RealFunction::Log10 => write!(f, "log10"),
+            RealFunction::Sin => write!(f, "sin"),
+            RealFunction::Cos => write!(f, "cos"),
+            RealFunction::Tan => write!(f, "tan"),
+            RealFunction::Asin => write!(f, "asin"),
+            RealFunction::Acos => write!(f, "acos"),
+            RealFunction::Atan => write!(f, "atan"),
+        }
+    }
+}
+
+/// Largest number of bits an exact power may have before it is treated as an overflow,
+/// and the largest number of bits an integer may be shifted by.
+pub const MAX_BITS: u64 = 1 << 24;
+
+// Math functions
+impl Numeric {
+    /// Returns the absolute value, keeping the type of the number.
+    pub fn abs(&self) -> Numeric {
+        match self {
+            Numeric::Integer(v) => match v.checked_abs() {
+                Some(v) => Numeric::Integer(v),
+                None => Numeric::from_big(BigInt::from(*v).abs()),
+            },
+            Numeric::BigInteger(v) => Numeric::BigInteger(v.abs()),
+            Numeric::Rational(v) => Numeric::Rational(v.abs()),
+            Numeric::Float(v) => Numeric::Float(v.abs()),
+            Numeric::NumError(_) => self.clone(),
+        }
+    }
+
+    /// Rounds the number to an integer. Floats that are not finite cannot be
+    /// rounded, and give a domain error.
+    ///
+    /// ```
+    /// use bprog::numeric::{Numeric, Rounding};
+    ///
+    /// assert_eq!(Numeric::Integer(-3), Numeric::Float(-2.5).round_to(Rounding::Round));
+    /// assert_eq!(Numeric::Integer(-2), "-5/2".parse::<Numeric>().unwrap().round_to(Rounding::Ceil));
+    /// ```
+    pub fn round_to(&self, mode: Rounding) -> Numeric {
+        let rounded = match self {
+            Numeric::Float(v) if !v.is_finite() => {
+                return Numeric::NumError(StackError::Domain(format!("cannot round {} to an integer", self)))
+            },
+            Numeric::Float(v) => Numeric::Float(match mode {
+                Rounding::Floor => v.floor(),
+                Rounding::Ceil => v.ceil(),
+                Rounding::Round => v.round(),
+                Rounding::Truncate => v.trunc(),
+            }),
+            Numeric::Rational(v) => Numeric::Rational(match mode {
+                Rounding::Floor => v.floor(),
+                Rounding::Ceil => v.ceil(),
+                Rounding::Round => v.round(),
+                Rounding::Truncate => v.trunc(),
+            }),
+            _ => self.clone(),
+        };
+        rounded.as_integer()
+    }
+
+    /// Computes a function on real numbers, giving a domain error for values the
+    /// function is not defined for, and an overflow error for results too large
+    /// for a float.
+    ///
+    /// ```
+    /// use bprog::numeric::{Numeric, RealFunction};
+    /// use bprog::stack_error::StackError;
+    ///
+    /// assert_eq!(Numeric::Float(3.0), Numeric::Integer(9).real(RealFunction::Sqrt));
+    /// assert!(matches!(Numeric::Integer(-1).real(RealFunction::Sqrt), Numeric::NumError(StackError::Domain(_))));
+    /// ```
+    pub fn real(&self, function: RealFunction) -> Numeric {
+        let x = match self.as_float() {
+            Numeric::Float(x) => x,
+            other => return other,
+        };
+        if x.is_infinite() && !matches!(self, Numeric::Float(_)) {
+            return Numeric::NumError(StackError::Overflow);
+        }
+        if !function.is_defined_for(x) {
+            return Numeric::NumError(StackError::Domain(format!("{} is not defined for {}", function, self)));
+        }
+        match function.apply(x) {
+            y if y.is_nan() => Numeric::NumError(StackError::Domain(format!("{} is not defined for {}", function, self))),
+            y if y.is_infinite() && x.is_finite() => Numeric::NumError(StackError::Overflow),
+            y => Numeric::Float(y),
+        }
+    }
+
+    /// Raises the number to a power. Integer and rational bases with an integer
+    /// exponent give exact results, where negative exponents give rationals. Other
+    /// powers are computed with floats.
+    ///
+    /// ```
+    /// use bprog::numeric::{Numeric, OverflowPolicy};
+    ///
+    /// let two = Numeric::Integer(2);
+    /// assert_eq!("1/8", two.pow(&Numeric::Integer(-3), OverflowPolicy::default()).to_string());
+    /// assert_eq!("1267650600228229401496703205376", two.pow(&Numeric::Integer(100), OverflowPolicy::default()).to_string());
+    /// ```
+    pub fn pow(&self, exp: &Numeric, policy: OverflowPolicy) -> Numeric {
+        match (self, exp) {
+            (Numeric::NumError(err), _) | (_, Numeric::NumError(err)) => Numeric::NumError(err.clone()),
+            (Numeric::Float(_), _) | (_, Numeric::Float(_) | Numeric::Rational(_)) => {
+                let (Numeric::Float(x), Numeric::Float(y)) = (self.as_float(), exp.as_float()) else {
+                    return Numeric::NumError(StackError::InvalidCoercion);
+                };
+                match x.powf(y) {
+                    z if z.is_nan() => Numeric::NumError(StackError::Domain(format!("pow is not defined for {} and {}", self, exp))),
+                    z if z.is_infinite() && x == 0.0 => Numeric::NumError(StackError::ZeroDiv),
+                    z if z.is_infinite() && x.is_finite() && y.is_finite() => Numeric::NumError(StackError::Overflow),
+                    z => Numeric::Float(z),
+                }
+            },
+            (base, _) => {
+                let base = base.to_ratio().unwrap_or_default();
+                let exp = exp.to_big().unwrap_or_default();
+                if base.is_zero() && exp.is_negative() {
+                    return Numeric::NumError(StackError::ZeroDiv);
+                }
+                // Powers of 0, 1 and -1 stay small whatever the exponent.
+                if exp.is_zero() {
+                    return Numeric::Integer(1);
+                }
+                if base.is_zero() || base.is_one() {
+                    return Numeric::from_ratio(base);
+                }
+                if base == -BigRational::one() {
+                    let odd = !(&exp % 2u8).is_zero();
+                    return Numeric::Integer(if odd { -1 } else { 1 });
+                }
+                let magnitude = match exp.abs().to_u32() {
+                    Some(m) if base.numer().bits().max(base.denom().bits()) * m as u64 <= MAX_BITS => m,
+                    _ => return Numeric::NumError(StackError::Overflow),
+                };
+                let result = BigRational::new(base.numer().pow(magnitude), base.denom().pow(magnitude));
+                let result = if exp.is_negative() { result.recip() } else { result };
+                if result.is_integer() {
+                    policy.fit(result.to_integer())
+                } else {
+                    Numeric::Rational(result)
+                }
+            }
+        }
+    }
+}
+
+// Integer functions
+impl Numeric {
+    /// Applies a function to two integers, giving an error for other numbers.
+    fn integer_op(&self, rhs: &Numeric, op: impl Fn(&BigInt, &BigInt) -> Numeric) -> Numeric {
+        match (self, rhs) {
+            (Numeric::NumError(err), _) | (_, Numeric::NumError(err)) => Numeric::NumError(err.clone()),
+            _ => match (self.to_big(), rhs.to_big()) {
+                (Some(v1), Some(v2)) => op(&v1, &v2),
+                _ => Numeric::NumError(StackError::InvalidCoercion),
+            }
+        }
+    }
+
+    /// Bitwise and of two integers. Negative integers behave as two's complement
+    /// with an unlimited number of bits, for this and the other bitwise functions.
+    pub fn bit_and(&self, rhs: &Numeric) -> Numeric {
+        self.integer_op(rhs, |a, b| Numeric::from_big(a & b))
+    }
+
+    /// Bitwise or of two integers.
+    pub fn bit_or(&self, rhs: &Numeric) -> Numeric {
+        self.integer_op(rhs, |a, b| Numeric::from_big(a | b))
+    }
+
+    /// Bitwise exclusive or of two integers.
+    pub fn bit_xor(&self, rhs: &Numeric) -> Numeric {
+        self.integer_op(rhs, |a, b| Numeric::from_big(a ^ b))
+    }
+
+    /// Bitwise complement of an integer, which is `-n - 1`.
+    pub fn bit_not(&self) -> Numeric {
+        self.integer_op(&Numeric::Integer(0), |a, _| Numeric::from_big(!a))
+    }
+
+    /// Shifts an integer left by a number of bits, using the policy when the result
+    /// does not fit in an i128. Shifting by a negative number, or by more than
+    /// `MAX_BITS`, gives an error.
+    ///
+    /// ```
+    /// use bprog::numeric::{Numeric, OverflowPolicy};
+    ///
+    /// let one = Numeric::Integer(1);
+    /// assert_eq!(Numeric::Integer(1024), one.shift_left(&Numeric::Integer(10), OverflowPolicy::default()));
+    /// assert_eq!(Numeric::Integer(0), one.shift_left(&Numeric::Integer(128), OverflowPolicy::Wrap));
+    /// assert!(matches!(one.shift_left(&Numeric::Integer(-1), OverflowPolicy::default()), Numeric::NumError(_)));
+    /// ```
+    pub fn shift_left(&self, amount: &Numeric, policy: OverflowPolicy) -> Numeric {
+        self.integer_op(amount, |a, b| match shift_amount(b) {
+            Ok(n) => policy.fit(a << n),
+            Err(err) => Numeric::NumError(err),
+        })
+    }
+
+    /// Shifts an integer right by a number of bits, rounding towards negative
+    /// infinity. The amount is limited like for `shift_left`.
+    pub fn shift_right(&self, amount: &Numeric) -> Numeric {
+        self.integer_op(amount, |a, b| match shift_amount(b) {
+            Ok(n) => Numeric::from_big(a >> n),
+            Err(err) => Numeric::NumError(err),
+        })
+    }
+
+    /// Counts the bits set in a non-negative integer.
+    pub fn popcount(&self) -> Numeric {
+        self.integer_op(&Numeric::Integer(0), |a, _| {
+            if a.is_negative() {
+                return Numeric::NumError(StackError::Domain(format!("popcount is not defined for {}", a)));
+            }
+            Numeric::Integer(a.magnitude().count_ones() as i128)
+        })
+    }
+
+    /// Greatest common divisor of two integers, which is never negative.
+    pub fn gcd(&self, rhs: &Numeric, policy: OverflowPolicy) -> Numeric {
+        self.integer_op(rhs, |a, b| policy.fit(a.gcd(b)))
+    }
+
+    /// Least common multiple of two integers, which is never negative.
+    pub fn lcm(&self, rhs: &Numeric, policy: OverflowPolicy) -> Numeric {
+        self.integer_op(rhs, |a, b| policy.fit(a.lcm(b)))
+    }
+
+    /// Square root of a non-negative integer, rounded down.
+    pub fn isqrt(&self) -> Numeric {
+        self.integer_op(&Numeric::Integer(0), |a, _| {
+            if a.is_negative() {
+                return Numeric::NumError(StackError::Domain(format!("isqrt is not defined for {}", a)));
+            }
+            Numeric::from_big(a.sqrt())
+        })
+    }
+
+    /// Writes an integer in base 2, 8 or 16, with the `0b`, `0o` or `0x` prefix
+    /// that reads back as the same number. Returns None for other numbers or bases.
+    ///
+    /// ```
+    /// use bprog::numeric::Numeric;
+    ///
+    /// assert_eq!(Some("-0xff".to_string()), Numeric::Integer(-255).to_radix_string(16));
+    /// assert_eq!(Numeric::Integer(-255), "-0xff".parse().unwrap());
+    /// ```
+    pub fn to_radix_string(&self, radix: u32) -> Option<String> {
+        let prefix = match radix {
+            2 => "0b",
+            8 => "0o",
+            16 => "0x",
+            _ => return None,
+        };
+        let val = self.to_big()?;
+        let sign = if val.is_negative() { "-" } else { "" };
+        Some(format!("{}{}{}", sign, prefix, val.magnitude().to_str_radix(radix)))
+    }
+}
+
+/// Checks that an integer is a valid number of bits to shift by.
+fn shift_amount(amount: &BigInt) -> Result<usize, StackError> {
+    match amount.to_u64() {
+        Some(n) if n <= MAX_BITS => Ok(n as usize),
+        _ => Err(StackError::InvalidArgument(format!("cannot shift by {} bits, shifts must be between 0 and {}", amount, MAX_BITS))),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// What integer arithmetic does when a result does not fit in an i128.
+pub enum OverflowPolicy {
+    /// Keep the exact result as a BigInteger.
+    #[default]
+    BigInt,
+    /// Give a numeric overflow error.
+    Error,
+    /// Wrap around in two's complement, as fixed width integers do.
+    Wrap,
+    /// Clamp the result to the smallest or largest i128.
+    Saturate,
+    /// Give the result as a float.
+    Float,
+}
+
+impl OverflowPolicy {
+    /// Handles an exact integer result according to the policy.
+    fn fit(&self, val: BigInt) -> Numeric {
+        if let Some(v) = val.to_i128() {
+            return Numeric::Integer(v);
+        }
+        match self {
+            OverflowPolicy::BigInt => Numeric::BigInteger(val),
+            OverflowPolicy::Error => Numeric::NumError(StackError::Overflow),
+            OverflowPolicy::Wrap => {
+                let low_bits = val & BigInt::from(u128::MAX);
+                Numeric::Integer(low_bits.to_u128().unwrap_or_default() as i128)
+            },
+            OverflowPolicy::Saturate => {
+                Numeric::Integer(if val.is_negative() { i128::MIN } else { i128::MAX })
+            },
+            OverflowPolicy::Float => Numeric::Float(val.to_f64().unwrap_or(f64::NAN)),
+        }
+    }
+}
+
+/// Implements Display for OverflowPolicy, giving the names used by programs.
+impl Display for OverflowPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OverflowPolicy::BigInt => write!(f, "bigint"),
+            OverflowPolicy::Error => write!(f, "error"),
+            OverflowPolicy::Wrap => write!(f, "wrap"),
+            OverflowPolicy::Saturate => write!(f, "saturate"),
+            OverflowPolicy::Float => write!(f, "float"),
+        }
+    }
+}
+
+/// Parses an OverflowPolicy from the name given by Display.
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bigint" => Ok(OverflowPolicy::BigInt),
+            "error" => Ok(OverflowPolicy::Error),
+            "wrap" => Ok(OverflowPolicy::Wrap),
+            "saturate" => Ok(OverflowPolicy::Saturate),
+            "float" => Ok(OverflowPolicy::Float),
+            _ => Err(format!("unknown overflow policy: {}", s)),
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The binary arithmetic operations on numbers.
+pub enum Arithmetic {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Arithmetic {
+    fn checked(&self) -> fn(i128, i128) -> Option<i128> {
+        match self {
+            Arithmetic::Add => i128::checked_add,
+            Arithmetic::Sub => i128::checked_sub,
+            Arithmetic::Mul => i128::checked_mul,
+            Arithmetic::Div => i128::checked_div,
+            Arithmetic::Rem => i128::checked_rem,
+        }
+    }
+
+    fn integer(&self) -> fn(&BigInt, &BigInt) -> Result<BigInt, StackError> {
+        match self {
+            Arithmetic::Add => try_int_add,
+            Arithmetic::Sub => try_int_sub,
+            Arithmetic::Mul => try_int_mul,
+            Arithmetic::Div => try_int_div,
+            Arithmetic::Rem => try_int_rem,
+        }
+    }
+
+    fn rational(&self) -> fn(&BigRational, &BigRational) -> Result<BigRational, StackError> {
+        match self {
+            Arithmetic::Add => try_rational_add,
+            Arithmetic::Sub => try_rational_sub,
+            Arithmetic::Mul => try_rational_mul,
+            Arithmetic::Div => try_rational_div,
+            Arithmetic::Rem => try_rational_rem,
+        }
+    }
+
+    fn float(&self) -> fn(f64, f64) -> Result<f64, StackError> {
+        match self {
+            Arithmetic::Add => try_add,
+            Arithmetic::Sub => try_sub,
+            Arithmetic::Mul => try_mul,
+            Arithmetic::Div => try_div,
+            Arithmetic::Rem => try_rem,
+        }
+    }
+}
+
+impl Numeric {
+    /// Applies an arithmetic operation, using the policy when an integer result does
+    /// not fit in an i128. The operator implementations for Numeric use the default
+    /// policy, which keeps such results exact as BigInteger.
+    ///
+    /// ```
+    /// use bprog::numeric::{Arithmetic, Numeric, OverflowPolicy};
+    ///
+    /// let max = Numeric::Integer(i128::MAX);
+    /// let one = Numeric::Integer(1);
+    ///
+    /// assert_eq!(Numeric::Integer(i128::MIN), max.arithmetic(Arithmetic::Add, &one, OverflowPolicy::Wrap));
+    /// assert_eq!(Numeric::Integer(i128::MAX), max.arithmetic(Arithmetic::Add, &one, OverflowPolicy::Saturate));
+    /// assert_eq!("170141183460469231731687303715884105728", (&max + &one).to_string());
+    /// ```
+    pub fn arithmetic(&self, op: Arithmetic, rhs: &Numeric, policy: OverflowPolicy) -> Numeric {
+        binary_numerical(self, rhs, op, policy)
     }
 }
 
 
 /// binary_numerical is a function that encapsulates binary operations for the Numeric enum type.
 ///
+/// Integer operations are done with checked i128 arithmetic. If that overflows, or an
+/// operand is a BigInteger, the exact result is computed and handled by the policy.
+///
 /// # Arguments
 ///
 /// * `lhs` - Left hand operand
 ///
 /// * `rhs`- Right hand operand
 ///
-/// * `op`- a binary function expecting two f64 values
-// TODO: Add specific op for int int operations to avoid problems with precision for i128?
-fn binary_numerical(lhs: &Numeric, rhs: &Numeric, op: fn(f64, f64) ->Result<f64, StackError>) -> Numeric {
+/// * `op`- the operation, giving the functions used for each kind of operand
+///
+/// * `policy`- what to do with integer results that do not fit in an i128
+fn binary_numerical(lhs: &Numeric, rhs: &Numeric, op: Arithmetic, policy: OverflowPolicy) -> Numeric {
+    if let (Numeric::Integer(v1), Numeric::Integer(v2)) = (lhs, rhs) {
+        if let Some(val) = op.checked()(*v1, *v2) {
+            return Numeric::Integer(val);
+        }
+    }
     match (lhs, rhs) {
         (Numeric::NumError(err), _) => Numeric::NumError(err.clone()),
         (_, Numeric::NumError(err)) => Numeric::NumError(err.clone()),
-        (Numeric::Integer(v1), Numeric::Integer(v2)) => {
-            match op(*v1 as f64, *v2 as f64) {
-                Ok(val) => Numeric::Integer(val as i128),
+        (Numeric::Integer(_) | Numeric::BigInteger(_), Numeric::Integer(_) | Numeric::BigInteger(_)) => {
+            let (v1, v2) = (lhs.to_big().unwrap_or_default(), rhs.to_big().unwrap_or_default());
+            match op.integer()(&v1, &v2) {
+                Ok(val) => policy.fit(val),
                 Err(e) => Numeric::NumError(e)
             }
         },
-        ( left,right) => {
-            match (left.as_float(), right.as_float()) {
-                (Numeric::Float(v1), Numeric::Float(v2)) => {
-                    match op(v1, v2) {
-                        Ok(val) => Numeric::Float(val),
-                        Err(e) => Numeric::NumError(e)
-                    }
+        (Numeric::Float(_), _) | (_, Numeric::Float(_)) => float_numerical(lhs, rhs, op.float()),
+        (left, right) => {
+            match (left.to_ratio(), right.to_ratio()) {
+                (Some(v1), Some(v2)) => match op.rational()(&v1, &v2) {
+                    Ok(val) => Numeric::from_ratio(val),
+                    Err(e) => Numeric::NumError(e)
                 },
-                // Should never occur if type system is properly set up.
-                (_, _)=> panic!("Encountered undefined type mismatch in binary_numerical.")
+                (_, _) => float_numerical(lhs, rhs, op.float()),
             }
         }
     }
 }
 
+/// Applies a binary f64 operation to two numbers converted to floats.
+fn float_numerical(lhs: &Numeric, rhs: &Numeric, op: fn(f64, f64) -> Result<f64, StackError>) -> Numeric {
+    match (lhs.as_float(), rhs.as_float()) {
+        (Numeric::Float(v1), Numeric::Float(v2)) => {
+            match op(v1, v2) {
+                Ok(val) => Numeric::Float(val),
+                Err(e) => Numeric::NumError(e)
+            }
+        },
+        // Should never occur if type system is properly set up.
+        (_, _)=> panic!("Encountered undefined type mismatch in binary_numerical.")
+    }
+}
+
+/// try_int_add is a function that adds two integers together and returns the result as a Result<BigInt, StackError>.
+fn try_int_add(a: &BigInt, b: &BigInt) -> Result<BigInt, StackError> {
+    Ok(a + b)
+}
+
+/// try_int_sub is a function that subtracts two integers and returns the result as a Result<BigInt, StackError>.
+fn try_int_sub(a: &BigInt, b: &BigInt) -> Result<BigInt, StackError> {
+    Ok(a - b)
+}
+
+/// try_int_mul is a function that multiplies two integers together and returns the result as a Result<BigInt, StackError>.
+fn try_int_mul(a: &BigInt, b: &BigInt) -> Result<BigInt, StackError> {
+    Ok(a * b)
+}
+
+/// try_int_div is a function that divides two integers, truncating towards zero, and returns the result as a
+/// Result<BigInt, StackError>. It returns a StackError with the ZeroDiv variant in the event of a zero divisor.
+fn try_int_div(a: &BigInt, b: &BigInt) -> Result<BigInt, StackError> {
+    if b.is_zero() {
+        Err(StackError::ZeroDiv)
+    } else {
+        Ok(a / b)
+    }
+}
+
+/// try_int_rem is a function that returns the remainder of dividing two integers as a Result<BigInt, StackError>.
+/// It returns a StackError with the ZeroDiv variant in the event of a zero divisor.
+fn try_int_rem(a: &BigInt, b: &BigInt) -> Result<BigInt, StackError> {
+    if b.is_zero() {
+        Err(StackError::ZeroDiv)
+    } else {
+        Ok(a % b)
+    }
+}
+
+/// try_rational_add is a function that adds two fractions together and returns the result as a Result<BigRational, StackError>.
+fn try_rational_add(a: &BigRational, b: &BigRational) -> Result<BigRational, StackError> {
+    Ok(a + b)
+}
+
+/// try_rational_sub is a function that subtracts two fractions and returns the result as a Result<BigRational, StackError>.
+fn try_rational_sub(a: &BigRational, b: &BigRational) -> Result<BigRational, StackError> {
+    Ok(a - b)
+}
+
+/// try_rational_mul is a function that multiplies two fractions together and returns the result as a Result<BigRational, StackError>.
+fn try_rational_mul(a: &BigRational, b: &BigRational) -> Result<BigRational, StackError> {
+    Ok(a * b)
+}
+
+/// try_rational_div is a function that divides two fractions exactly and returns the result as a Result<BigRational, StackError>.
+/// It returns a StackError with the ZeroDiv variant in the event of a zero divisor.
+fn try_rational_div(a: &BigRational, b: &BigRational) -> Result<BigRational, StackError> {
+    if b.is_zero() {
+        Err(StackError::ZeroDiv)
+    } else {
+        Ok(a / b)
+    }
+}
+
+/// try_rational_rem is a function that returns the remainder of dividing two fractions as a Result<BigRational, StackError>.
+/// It returns a StackError with the ZeroDiv variant in the event of a zero divisor.
+fn try_rational_rem(a: &BigRational, b: &BigRational) -> Result<BigRational, StackError> {
+    if b.is_zero() {
+        Err(StackError::ZeroDiv)
+    } else {
+        Ok(a % b)
+    }
+}
+
 /// try_add is a function that adds two f64 numbers together and returns the result as a Result<f64, StackError>.
 /// It handles any errors related to the arithmetic operation, returning a StackError in the event of a failed operation.
 fn try_add(a: f64, b: f64) -> Result<f64, StackError> {
@@ -281,26 +973,12 @@ fn try_div(a: f64, b: f64) -> Result<f64, StackError> {
     }
 }
 
-/// try_lt is a function that compares two f64 numbers and returns 1.0 if the first is less than the second, and 0.0 otherwise.
-/// It handles any errors related to the logical operation, returning a StackError in the event of a failed operation.
-fn try_lt(a: f64, b: f64) -> Result<f64, StackError> {
-    Ok((a < b) as i32 as f64)
-}
-
-/// try_le is a function that compares two f64 numbers and returns 1.0 if the first is less than or equal to the second, and 0.0 otherwise.
-/// It handles any errors related to the logical operation, returning a StackError in the event of a failed operation.
-fn try_le(a: f64, b: f64) -> Result<f64, StackError> {
-    Ok((a <= b) as i32 as f64)
-}
-
-/// try_gt is a function that compares two f64 numbers and returns 1.0 if the first is greater than the second, and 0.0 otherwise.
-/// It handles any errors related to the logical operation, returning a StackError in the event of a failed operation.
-fn try_gt(a: f64, b: f64) -> Result<f64, StackError> {
-    Ok((a > b) as i32 as f64)
-}
-
-/// try_ge is a function that compares two f64 numbers and returns 1.0 if the first is greater than or equal to the second, and 0.0 otherwise.
-/// It handles any errors related to the logical operation, returning a StackError in the event of a failed operation.
-fn try_ge(a: f64, b: f64) -> Result<f64, StackError> {
-    Ok((a >= b) as i32 as f64)
+/// try_rem is a function that returns the remainder of dividing two f64 numbers as a Result<f64, StackError>.
+/// It returns a StackError with the ZeroDiv variant in the event of a zero divisor.
+fn try_rem(a: f64, b: f64) -> Result<f64, StackError> {
+    if b != 0.0 {
+        Ok(a % b)
+    } else {
+        Err(StackError::ZeroDiv)
+    }
 }
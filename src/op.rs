@@ -2,16 +2,18 @@
 /////////////////////////// OP ////////////////////////////////////////////////////////////////////
 
 use std::{fmt, io};
-use std::collections::{HashMap, VecDeque};
 use std::fmt::{Display, Formatter};
 use std::io::{Write};
+use std::rc::Rc;
 use std::str::FromStr;
-use crate::interpreter::{Args, Binding};
-use crate::numeric::Numeric;
+use num::{BigInt, ToPrimitive};
+use crate::interpreter::{as_code, Args, Binding, Env};
+use crate::name::Name;
+use crate::numeric::{Arithmetic, Numeric, OverflowPolicy, RealFunction, Rounding};
 use crate::parsed::Parsed;
-use crate::parsing::{ parse_to_quotation};
+use crate::random::Rng;
 use crate::stack_error::StackError;
-use crate::types::{Params, Constraint, heterogeneous_binary, homogenous_binary, nullary, Signature, Type, unary};
+use crate::types::{Params, Constraint, heterogeneous_binary, homogenous_binary, nullary, numeric_coercion, Signature, Type, unary};
 
 
 #[derive(Clone, PartialEq)]
@@ -28,6 +30,51 @@ pub enum Op {
     Mul,
     Div,
     IntDiv,
+    Overflow,
+    SetOverflow,
+    DepthLimit,
+    SetDepthLimit,
+    Numerator,
+    Denominator,
+    ToFloat,
+    Abs,
+    Min,
+    Max,
+    Pow,
+    Sqrt,
+    Exp,
+    Ln,
+    Log10,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Floor,
+    Ceil,
+    Round,
+    Truncate,
+    Pi,
+    E,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
+    PopCount,
+    Gcd,
+    Lcm,
+    ISqrt,
+    ToHex,
+    ToBinary,
+    ToOctal,
+    RandInt,
+    RandFloat,
+    Shuffle,
+    Sample,
+    Seed,
     LT,
     GT,
     EQ,
@@ -47,6 +94,12 @@ pub enum Op {
     Loop,
     Times,
     Exec,
+    Global,
+    /// Binds the names of a quotation header, written `a b ->`, to values popped off
+    /// the stack, the last name to the top value.
+    Locals(Vec<Name>),
+    Import,
+    Use,
     Assign,
     AssignFunc,
     AsSymbol,
@@ -55,57 +108,63 @@ pub enum Op {
     Swap,
     Pop,
     Mod,
-    Error
+    Error,
+    Try,
+    Ensure,
+    Throw,
+    ErrorMessage,
+    ErrorPayload,
+    Just,
+    Nothing,
+    Ok,
+    Failure,
+    UnwrapOr,
+    Case,
+    SafeParseInt,
+    SafeParseFloat,
+    SafeHead,
+    SafeTail,
 }
 
 
 impl Op {
 
-    pub fn exec_nullary(&self, mods: Args, _bindings: &mut HashMap<String, Binding>) -> Parsed {
+    pub fn exec_nullary(&self, mods: Args, env: &mut Env) -> Parsed {
         match self {
             Op::IORead => Self::exec_ioread(),
             Op::Void => Self::exec_void(),
             Op::AsSymbol => Self::exec_as_symbol(mods),
-            Op::Loop => Self::exec_loop(mods),
             Op::Error => Self::exec_err(mods),
+            Op::Overflow => Self::exec_overflow(env),
+            Op::DepthLimit => Parsed::Num(Numeric::Integer(env.depth_limit as i128)),
+            Op::Pi => Parsed::Num(Numeric::Float(std::f64::consts::PI)),
+            Op::E => Parsed::Num(Numeric::Float(std::f64::consts::E)),
+            Op::RandFloat => Parsed::Num(Numeric::Float(env.rng.next_float())),
+            Op::Nothing => Parsed::Maybe(None),
             _ => panic!("bug:  use of wrong exec_* function for function {}", self)
         }
     }
 
-    fn exec_loop(c: Args) -> Parsed {
-        match c {
-            Args::Binary(mut quot1, mut quot2) => {
-                quot1 = quot1.coerce(&Type::Quotation);
-                quot2 = quot2.coerce(&Type::Quotation);
-                let ret = format!(
-                    " {:?} exec not if {{ {:?} exec loop {:?} {:?} }} {{ }} ",
-                    quot1,  quot2, quot1, quot2);
-                return parse_to_quotation(ret)
-            }
-            _ => panic!("invalid closure count sent to each function"),
-        }
-    }
-
     fn exec_as_symbol(c: Args) -> Parsed {
-        if let Args::Unary(Parsed::Symbol(s)) = c {
-            return Parsed::Symbol(s.clone());
+        if let Args::Unary(Parsed::Symbol(s, span)) = c {
+            return Parsed::Symbol(s, span);
         }
         panic!("bug: function ' (eval as symbol) fed non symbol as modifier. Check constraints.")
     }
 
-    fn exec_eval(arg: Parsed, bindings: &mut HashMap<String, Binding>) -> Parsed {
-        if let Parsed::Symbol(s) = arg {
-            return if let Some(binding) = bindings.get(s.as_str()) {
-                binding.value.clone()
+    fn exec_eval(arg: Parsed, env: &mut Env) -> Parsed {
+        if let Parsed::Symbol(s, span) = arg {
+            return if let Some(binding) = env.lookup(&s) {
+                binding.value
             } else {
-                Parsed::Symbol(s.clone())
+                Parsed::Symbol(s, span)
             }
         }
         panic!("bug: function ' (eval as symbol) fed non symbol as modifier. Check constraints.")
     }
 
 
-    pub fn exec_unary(&self, arg: Parsed, c: Args, bindings: &mut  HashMap<String, Binding>) -> Parsed {
+    pub fn exec_unary(&self, arg: Parsed, c: Args, env: &mut Env) -> Parsed {
         match self {
             Op::IOPrint => Self::exec_print(arg),
             Op::ParseInt => Self::exec_parse_int(arg),
@@ -116,49 +175,101 @@ impl Op {
             Op::Head => Self::exec_head(arg),
             Op::Tail => Self::exec_tail(arg),
             Op::Not => Self::exec_not(arg),
+            Op::Numerator => Self::exec_numerator(arg),
+            Op::Denominator => Self::exec_denominator(arg),
+            Op::ToFloat => Self::exec_to_float(arg),
+            Op::SetOverflow => Self::exec_set_overflow(arg, env),
+            Op::SetDepthLimit => Self::exec_set_depth_limit(arg, env),
+            Op::Abs => Self::exec_abs(arg),
+            Op::Sqrt => Self::exec_real(arg, RealFunction::Sqrt),
+            Op::Exp => Self::exec_real(arg, RealFunction::Exp),
+            Op::Ln => Self::exec_real(arg, RealFunction::Ln),
+            Op::Log10 => Self::exec_real(arg, RealFunction::Log10),
+            Op::Sin => Self::exec_real(arg, RealFunction::Sin),
+            Op::Cos => Self::exec_real(arg, RealFunction::Cos),
+            Op::Tan => Self::exec_real(arg, RealFunction::Tan),
+            Op::Asin => Self::exec_real(arg, RealFunction::Asin),
+            Op::Acos => Self::exec_real(arg, RealFunction::Acos),
+            Op::Atan => Self::exec_real(arg, RealFunction::Atan),
+            Op::Floor => Self::exec_round(arg, Rounding::Floor),
+            Op::Ceil => Self::exec_round(arg, Rounding::Ceil),
+            Op::Round => Self::exec_round(arg, Rounding::Round),
+            Op::Truncate => Self::exec_round(arg, Rounding::Truncate),
+            Op::BitNot => Self::exec_integer(arg, Numeric::bit_not),
+            Op::PopCount => Self::exec_integer(arg, Numeric::popcount),
+            Op::ISqrt => Self::exec_integer(arg, Numeric::isqrt),
+            Op::ToHex => Self::exec_to_radix(arg, 16),
+            Op::ToBinary => Self::exec_to_radix(arg, 2),
+            Op::ToOctal => Self::exec_to_radix(arg, 8),
+            Op::Shuffle => Self::exec_shuffle(arg, env),
+            Op::Seed => Self::exec_seed(arg, env),
             Op::Pop => Self::exec_pop(arg),
             Op::Dup => Self::exec_dup(arg),
-            Op::Exec => Self::exec_exec(arg),
-            Op::If => Self::exec_if(arg, c),
-            Op::Times => Self::exec_times(arg, c),
-            Op::Map => Self::exec_map(arg, c),
-            Op::Each => Self::exec_each(arg, c),
-            Op::EvalSymbol => Self::exec_eval(arg, bindings),
+            Op::Exec => Self::exec_exec(arg, env),
+            Op::If => Self::exec_if(arg, c, env),
+            Op::EvalSymbol => Self::exec_eval(arg, env),
+            Op::Throw => Self::exec_throw(arg),
+            Op::ErrorMessage => Self::exec_error_message(arg),
+            Op::ErrorPayload => Self::exec_error_payload(arg),
+            Op::Just => Parsed::just(arg),
+            Op::Ok => Parsed::ok(arg),
+            Op::Failure => Parsed::failure(arg),
+            Op::SafeParseInt => Self::exec_safe_parse_int(arg),
+            Op::SafeParseFloat => Self::exec_safe_parse_float(arg),
+            Op::SafeHead => Self::exec_safe_head(arg),
+            Op::SafeTail => Self::exec_safe_tail(arg),
             _ => panic!("bug:  use of wrong exec_* function for function {}", self)
         }
     }
 
-    pub fn exec_binary(&self, lhs: &Parsed, rhs: &Parsed, c: Args, bindings: &mut HashMap<String, Binding>) -> Parsed {
+    pub fn exec_binary(&self, lhs: Parsed, rhs: Parsed, c: Args, env: &mut Env) -> Parsed {
+        let (lhs, rhs) = match self {
+            // These take their operands, so that lists only referred to from the stack
+            // can be changed in place.
+            Op::Append => return Self::exec_append(lhs, rhs),
+            Op::Cons => return Self::exec_cons(lhs, rhs),
+            Op::Swap => return Self::exec_swap(lhs, rhs),
+            _ => (&lhs, &rhs),
+        };
         match self {
-            Op::Mod => Self::exec_mod(lhs, rhs),
-            Op::Add => Self::exec_add(lhs, rhs),
-            Op::Sub => Self::exec_sub(lhs, rhs),
-            Op::Mul => Self::exec_mul(lhs, rhs),
-            Op::Div => Self::exec_div(lhs, rhs),
-            Op::IntDiv => Self::exec_intdiv(lhs, rhs),
+            Op::Mod => Self::exec_mod(lhs, rhs, env.overflow),
+            Op::UnwrapOr => Self::exec_unwrap_or(lhs, rhs),
+            Op::Add => Self::exec_add(lhs, rhs, env.overflow),
+            Op::Sub => Self::exec_sub(lhs, rhs, env.overflow),
+            Op::Mul => Self::exec_mul(lhs, rhs, env.overflow),
+            Op::Div => Self::exec_div(lhs, rhs, env.overflow),
+            Op::IntDiv => Self::exec_intdiv(lhs, rhs, env.overflow),
+            Op::Pow => Self::exec_pow(lhs, rhs, env.overflow),
+            Op::Min => Self::exec_min(lhs, rhs),
+            Op::Max => Self::exec_max(lhs, rhs),
+            Op::BitAnd => Self::exec_integers(lhs, rhs, Numeric::bit_and),
+            Op::BitOr => Self::exec_integers(lhs, rhs, Numeric::bit_or),
+            Op::BitXor => Self::exec_integers(lhs, rhs, Numeric::bit_xor),
+            Op::ShiftLeft => Self::exec_integers(lhs, rhs, |a, b| a.shift_left(b, env.overflow)),
+            Op::ShiftRight => Self::exec_integers(lhs, rhs, Numeric::shift_right),
+            Op::Gcd => Self::exec_integers(lhs, rhs, |a, b| a.gcd(b, env.overflow)),
+            Op::Lcm => Self::exec_integers(lhs, rhs, |a, b| a.lcm(b, env.overflow)),
+            Op::RandInt => Self::exec_rand_int(lhs, rhs, env),
+            Op::Sample => Self::exec_sample(lhs, rhs, env),
             Op::GT => Self::exec_gt(lhs, rhs),
             Op::LT => Self::exec_lt(lhs, rhs),
             Op::EQ => Self::exec_eq(lhs, rhs),
             Op::And => Self::exec_and(lhs, rhs),
             Op::Or => Self::exec_or(lhs, rhs),
-            Op::Append => Self::exec_append(lhs, rhs),
-            Op::Cons => Self::exec_cons(lhs, rhs),
-            Op::Swap => Self::exec_swap(lhs, rhs),
-            Op::Foldl => Self::exec_foldl(lhs, rhs, c),
-            Op::Assign => Self::exec_assign(lhs, rhs, c, bindings, false),
-            Op::AssignFunc => Self::exec_assign(lhs, rhs, c, bindings, true),
+            Op::Assign => Self::exec_assign(lhs, rhs, c, env, false),
+            Op::AssignFunc => Self::exec_assign(lhs, rhs, c, env, true),
             _ => panic!("bug:  use of wrong exec_* function for function {}, or function not implemented.", self)
         }
     }
 
 
-    fn exec_assign(lhs: &Parsed, rhs: &Parsed, _c: Args, bindings: &mut HashMap<String, Binding>, func: bool) -> Parsed {
+    fn exec_assign(lhs: &Parsed, rhs: &Parsed, _c: Args, env: &mut Env, func: bool) -> Parsed {
         if func && !Constraint::Executable.is_satisfied_by(&rhs.get_type()) {
             panic!("bug: non executable value attempted bound to function. Check constraint system.")
         }
         match lhs {
-            Parsed::Symbol(s) => {
-                if let Some (val) = bindings.get(s.as_str()) {
+            Parsed::Symbol(s, _) => {
+                if let Some (val) = env.lookup(s) {
                     if val.constant {
                         return Parsed::Error(StackError::Undefined);
                     }
@@ -168,7 +279,7 @@ impl Op {
                     constant: false,
                     value: rhs.clone(),
                 };
-                bindings.insert(s.clone(), binding);
+                env.assign(s, binding);
                 Parsed::Void
 
             },
@@ -197,9 +308,9 @@ impl Op {
         print!("input : ");
         io::stdout().flush().unwrap();
         let mut string = String::new();
-        if let Ok(_) = io::stdin().read_line(&mut string) {
+        if io::stdin().read_line(&mut string).is_ok() {
             string.pop();
-            Parsed::String(string)
+            Parsed::string(string)
         } else {
             panic!("bug: failed to read from stdin.")
         }
@@ -215,27 +326,48 @@ impl Op {
     //// PARSING FUNCTION DEFINITIONS ////
 
     pub fn exec_parse_int(arg: Parsed) -> Parsed {
+        match Self::parse_int(&arg) {
+            Ok(i) => Parsed::Num(i),
+            Err(_) => Parsed::Error(StackError::Overflow),
+        }
+    }
+
+    pub fn exec_parse_float(arg: Parsed) -> Parsed {
+        match Self::parse_float(&arg) {
+            Ok(f) => Parsed::Num(f),
+            Err(_) => Parsed::Error(StackError::Overflow),
+        }
+    }
+
+    fn exec_safe_parse_int(arg: Parsed) -> Parsed {
+        match Self::parse_int(&arg) {
+            Ok(i) => Parsed::ok(Parsed::Num(i)),
+            Err(s) => Parsed::failure(Parsed::string(format!("cannot parse '{}' as an integer", s))),
+        }
+    }
+
+    fn exec_safe_parse_float(arg: Parsed) -> Parsed {
+        match Self::parse_float(&arg) {
+            Ok(f) => Parsed::ok(Parsed::Num(f)),
+            Err(s) => Parsed::failure(Parsed::string(format!("cannot parse '{}' as a float", s))),
+        }
+    }
+
+    /// Parses a string as an integer, or gives back the string if it is not one.
+    fn parse_int(arg: &Parsed) -> Result<Numeric, &str> {
         match arg {
-            Parsed::String(s) => {
-                return if let Ok(i) = s.parse::<i128>() {
-                    Parsed::Num(Numeric::Integer(i))
-                } else {
-                    Parsed::Error(StackError::Overflow)
-                }
+            Parsed::String(s) => match s.parse::<Numeric>() {
+                Ok(i @ (Numeric::Integer(_) | Numeric::BigInteger(_))) => Ok(i),
+                _ => Err(s),
             },
             _ => panic!("bug: argument type not implemented for parseInteger")
         }
     }
 
-    pub fn exec_parse_float(arg: Parsed) -> Parsed {
+    /// Parses a string as a float, or gives back the string if it is not one.
+    fn parse_float(arg: &Parsed) -> Result<Numeric, &str> {
         match arg {
-            Parsed::String(s) => {
-                return if let Ok(f) = s.parse::<f64>() {
-                    Parsed::Num(Numeric::Float(f))
-                } else {
-                    Parsed::Error(StackError::Overflow)
-                }
-            },
+            Parsed::String(s) => s.parse::<f64>().map(Numeric::Float).map_err(|_| s.as_str()),
             _ => panic!("bug: argument type not implemented for parseFloat")
         }
     }
@@ -243,9 +375,9 @@ impl Op {
     pub fn exec_words(arg: Parsed) -> Parsed {
         match arg {
             Parsed::String(s) => {
-                Parsed::List(
+                Parsed::list(
                     s.split_whitespace()
-                        .map(|s| Parsed::String(s.to_string()))
+                        .map(Parsed::string)
                         .collect::<Vec<Parsed>>(),
                 )
             }
@@ -256,31 +388,206 @@ impl Op {
 
 
     //// ARITHMETIC, ORDERING, EQ, BOOLEAN FUNCTION DEFINITIONS ////
-    pub fn exec_mod(lhs: &Parsed, rhs: &Parsed) -> Parsed {
-        if let (Parsed::Num(Numeric::Integer(i)), Parsed::Num(Numeric::Integer(i2))) = (lhs, rhs) {
-            return Parsed::Num(Numeric::Integer(i % i2));
+    pub fn exec_mod(lhs: &Parsed, rhs: &Parsed, policy: OverflowPolicy) -> Parsed {
+        if let (Parsed::Num(_), Parsed::Num(_)) = (lhs, rhs) {
+            return lhs.arithmetic(Arithmetic::Rem, rhs, policy);
         }
         panic!("bug: non integer passed to modulo operation");
     }
 
-    pub fn exec_add(lhs: &Parsed, rhs: &Parsed) -> Parsed {
-        lhs + rhs
+    pub fn exec_add(lhs: &Parsed, rhs: &Parsed, policy: OverflowPolicy) -> Parsed {
+        match (lhs, rhs) {
+            (Parsed::Num(_), Parsed::Num(_)) => lhs.arithmetic(Arithmetic::Add, rhs, policy),
+            _ => lhs + rhs
+        }
+    }
+
+    pub fn exec_sub(lhs: &Parsed, rhs: &Parsed, policy: OverflowPolicy) -> Parsed {
+        lhs.arithmetic(Arithmetic::Sub, rhs, policy)
+    }
+
+    pub fn exec_mul(lhs: &Parsed, rhs: &Parsed, policy: OverflowPolicy) -> Parsed {
+        lhs.arithmetic(Arithmetic::Mul, rhs, policy)
+    }
+
+    /// Divides exactly if either operand is a rational, and as floats otherwise.
+    pub fn exec_div(lhs: &Parsed, rhs: &Parsed, policy: OverflowPolicy) -> Parsed {
+        match numeric_coercion(&lhs.get_type(), &rhs.get_type()) {
+            Some(Type::Rational) => lhs.coerce(&Type::Rational)
+                .arithmetic(Arithmetic::Div, &rhs.coerce(&Type::Rational), policy),
+            _ => &lhs.coerce(&Type::Float) / &rhs.coerce(&Type::Float)
+        }
+    }
+
+    pub fn exec_intdiv(lhs: &Parsed, rhs: &Parsed, policy: OverflowPolicy) -> Parsed {
+        match numeric_coercion(&lhs.get_type(), &rhs.get_type()) {
+            Some(Type::Rational) => Self::exec_div(lhs, rhs, policy).coerce(&Type::Integer),
+            _ => lhs.coerce(&Type::Integer)
+                .arithmetic(Arithmetic::Div, &rhs.coerce(&Type::Integer), policy)
+        }
+    }
+
+    /// Returns the name of the overflow policy in use.
+    pub fn exec_overflow(env: &Env) -> Parsed {
+        Parsed::string(env.overflow.to_string())
+    }
+
+    /// Switches the overflow policy used by integer arithmetic from here on.
+    pub fn exec_set_overflow(arg: Parsed, env: &mut Env) -> Parsed {
+        match arg {
+            Parsed::String(s) => match s.parse::<OverflowPolicy>() {
+                Ok(policy) => {
+                    env.overflow = policy;
+                    Parsed::Void
+                },
+                Err(message) => Parsed::Error(StackError::InvalidArgument(message)),
+            },
+            _ => panic!("bug: argument type not implemented for setOverflow")
+        }
+    }
+
+    /// Changes how many calls may be nested, where 0 removes the limit.
+    pub fn exec_set_depth_limit(arg: Parsed, env: &mut Env) -> Parsed {
+        match arg {
+            Parsed::Num(n) => match n.to_big().and_then(|n| n.to_usize()) {
+                Some(limit) => {
+                    env.depth_limit = limit;
+                    Parsed::Void
+                },
+                None => Parsed::Error(StackError::InvalidArgument(format!("invalid depth limit {}", n))),
+            },
+            _ => panic!("bug: argument type not implemented for setDepthLimit")
+        }
     }
 
-    pub fn exec_sub(lhs: &Parsed, rhs: &Parsed) -> Parsed {
-        lhs - rhs
+    pub fn exec_numerator(arg: Parsed) -> Parsed {
+        match arg {
+            Parsed::Num(n) => Parsed::Num(n.numerator().expect("constrained to exact numbers")),
+            _ => panic!("bug: argument type not implemented for numerator")
+        }
     }
 
-    pub fn exec_mul(lhs: &Parsed, rhs: &Parsed) -> Parsed {
-        lhs * rhs
+    pub fn exec_denominator(arg: Parsed) -> Parsed {
+        match arg {
+            Parsed::Num(n) => Parsed::Num(n.denominator().expect("constrained to exact numbers")),
+            _ => panic!("bug: argument type not implemented for denominator")
+        }
+    }
+
+    pub fn exec_to_float(arg: Parsed) -> Parsed {
+        arg.coerce(&Type::Float)
+    }
+
+    //// MATH FUNCTION DEFINITIONS ////
+
+    /// Returns the number held by a numeric argument, with booleans as 0 or 1.
+    fn to_numeric(arg: &Parsed) -> Numeric {
+        match arg {
+            Parsed::Num(n) => n.clone(),
+            Parsed::Bool(b) => Numeric::Integer(*b as i128),
+            _ => panic!("bug: non numeric value passed to math function. Check constraints.")
+        }
     }
 
-    pub fn exec_div(lhs: &Parsed, rhs: &Parsed) -> Parsed {
-        &lhs.coerce(&Type::Float) / &rhs.coerce(&Type::Float)
+    pub fn exec_abs(arg: Parsed) -> Parsed {
+        Parsed::from_numeric(Self::to_numeric(&arg).abs())
     }
 
-    pub fn exec_intdiv(lhs: &Parsed, rhs: &Parsed) -> Parsed {
-        (&lhs.coerce(&Type::Integer) / &rhs.coerce(&Type::Integer)).coerce(&Type::Integer)
+    /// Returns the smaller operand unchanged, preferring the first if they are equal.
+    pub fn exec_min(lhs: &Parsed, rhs: &Parsed) -> Parsed {
+        if rhs < lhs { rhs.clone() } else { lhs.clone() }
+    }
+
+    /// Returns the larger operand unchanged, preferring the first if they are equal.
+    pub fn exec_max(lhs: &Parsed, rhs: &Parsed) -> Parsed {
+        if rhs > lhs { rhs.clone() } else { lhs.clone() }
+    }
+
+    pub fn exec_pow(lhs: &Parsed, rhs: &Parsed, policy: OverflowPolicy) -> Parsed {
+        Parsed::from_numeric(Self::to_numeric(lhs).pow(&Self::to_numeric(rhs), policy))
+    }
+
+    pub fn exec_real(arg: Parsed, function: RealFunction) -> Parsed {
+        Parsed::from_numeric(Self::to_numeric(&arg).real(function))
+    }
+
+    pub fn exec_round(arg: Parsed, mode: Rounding) -> Parsed {
+        Parsed::from_numeric(Self::to_numeric(&arg).round_to(mode))
+    }
+
+    //// INTEGER FUNCTION DEFINITIONS ////
+
+    pub fn exec_integer(arg: Parsed, function: fn(&Numeric) -> Numeric) -> Parsed {
+        match arg {
+            Parsed::Num(n) => Parsed::from_numeric(function(&n)),
+            _ => panic!("bug: non integer passed to integer function. Check constraints.")
+        }
+    }
+
+    pub fn exec_integers(lhs: &Parsed, rhs: &Parsed, function: impl Fn(&Numeric, &Numeric) -> Numeric) -> Parsed {
+        match (lhs, rhs) {
+            (Parsed::Num(n1), Parsed::Num(n2)) => Parsed::from_numeric(function(n1, n2)),
+            _ => panic!("bug: non integer passed to integer function. Check constraints.")
+        }
+    }
+
+    pub fn exec_to_radix(arg: Parsed, radix: u32) -> Parsed {
+        match arg {
+            Parsed::Num(n) => Parsed::string(n.to_radix_string(radix).expect("constrained to integers")),
+            _ => panic!("bug: non integer passed to radix conversion. Check constraints.")
+        }
+    }
+
+    //// RANDOM FUNCTION DEFINITIONS ////
+
+    /// Picks an integer between two bounds, both included.
+    pub fn exec_rand_int(lhs: &Parsed, rhs: &Parsed, env: &mut Env) -> Parsed {
+        match (lhs, rhs) {
+            (Parsed::Num(n1), Parsed::Num(n2)) => {
+                let (low, high) = (n1.to_big().expect("constrained to integers"), n2.to_big().expect("constrained to integers"));
+                if low > high {
+                    return Parsed::Error(StackError::InvalidArgument(
+                        format!("randInt needs a lower bound no greater than the upper bound, got {} and {}", low, high)));
+                }
+                Parsed::Num(Numeric::from_big(env.rng.between(&low, &high)))
+            },
+            _ => panic!("bug: non integer passed to randInt. Check constraints.")
+        }
+    }
+
+    pub fn exec_shuffle(arg: Parsed, env: &mut Env) -> Parsed {
+        match arg {
+            Parsed::List(mut items) => {
+                env.rng.shuffle(Rc::make_mut(&mut items).as_mut_slice());
+                Parsed::List(items)
+            },
+            _ => panic!("bug: non list passed to shuffle. Check constraints.")
+        }
+    }
+
+    /// Picks a number of distinct elements from a list, in random order.
+    pub fn exec_sample(lhs: &Parsed, rhs: &Parsed, env: &mut Env) -> Parsed {
+        match (lhs, rhs) {
+            (Parsed::List(items), Parsed::Num(n)) => match n.to_big().and_then(|n| n.to_usize()) {
+                Some(n) if n <= items.len() => Parsed::list(env.rng.sample(items, n)),
+                _ => Parsed::Error(StackError::InvalidArgument(
+                    format!("cannot sample {} elements from a list of {}", n, items.len()))),
+            },
+            _ => panic!("bug: invalid arguments passed to sample. Check constraints.")
+        }
+    }
+
+    /// Restarts the random number generator from a seed. Only the lowest 64 bits of
+    /// the seed are used.
+    pub fn exec_seed(arg: Parsed, env: &mut Env) -> Parsed {
+        match arg {
+            Parsed::Num(n) => {
+                let bits = n.to_big().expect("constrained to integers") & BigInt::from(u64::MAX);
+                env.rng = Rng::new(bits.to_u64().unwrap_or_default());
+                Parsed::Void
+            },
+            _ => panic!("bug: non integer passed to seed. Check constraints.")
+        }
     }
 
     pub fn exec_gt(lhs: &Parsed, rhs: &Parsed) -> Parsed {
@@ -320,7 +627,7 @@ impl Op {
     pub fn exec_head(arg: Parsed) -> Parsed {
         match arg {
             Parsed::List(v) => {
-                v.get(0).unwrap_or_else(||&Parsed::Error(StackError::HeadEmpty)).clone()
+                v.first().unwrap_or(&Parsed::Error(StackError::HeadEmpty)).clone()
             }
             _ => panic!("head not supported for {}", arg),
         }
@@ -328,23 +635,61 @@ impl Op {
 
     pub fn exec_tail(arg: Parsed) -> Parsed {
         match arg {
-            Parsed::List(v) => {
+            Parsed::List(mut v) => {
                 if !v.is_empty() {
-                    Parsed::List(v[1..].to_vec())
+                    Rc::make_mut(&mut v).remove(0);
+                    Parsed::List(v)
                 } else {
-                    panic!("exec_tail: TODO")
+                    Parsed::Error(StackError::TailEmpty)
                 }
             }
             _ => panic!("tail not support"),
         }
     }
 
-    pub fn exec_append(lhs: &Parsed, rhs: &Parsed) -> Parsed {
-        lhs + rhs
+    /// Like head, but gives Just the first element, or Nothing for an empty list.
+    fn exec_safe_head(arg: Parsed) -> Parsed {
+        match Self::exec_head(arg) {
+            Parsed::Error(_) => Parsed::Maybe(None),
+            head => Parsed::just(head),
+        }
     }
 
-    pub fn exec_cons(lhs: &Parsed, rhs: &Parsed) -> Parsed {
-        &Parsed::List(vec![lhs.clone()]) + rhs
+    /// Like tail, but gives Just the remaining elements, or Nothing for an empty list.
+    fn exec_safe_tail(arg: Parsed) -> Parsed {
+        match Self::exec_tail(arg) {
+            Parsed::Error(_) => Parsed::Maybe(None),
+            tail => Parsed::just(tail),
+        }
+    }
+
+    /// Gives the value of Just or Ok, or else the default.
+    fn exec_unwrap_or(lhs: &Parsed, rhs: &Parsed) -> Parsed {
+        match lhs {
+            Parsed::Maybe(Some(value)) | Parsed::Result(Ok(value)) => value.as_ref().clone(),
+            Parsed::Maybe(None) | Parsed::Result(Err(_)) => rhs.clone(),
+            _ => panic!("bug: invalid arguments sent to unwrapOr. Check constraints."),
+        }
+    }
+
+    pub fn exec_append(lhs: Parsed, rhs: Parsed) -> Parsed {
+        match (lhs, rhs) {
+            (Parsed::List(mut l1), Parsed::List(l2)) => {
+                Rc::make_mut(&mut l1).extend(l2.iter().cloned());
+                Parsed::List(l1)
+            },
+            (lhs, rhs) => &lhs + &rhs,
+        }
+    }
+
+    pub fn exec_cons(lhs: Parsed, rhs: Parsed) -> Parsed {
+        match rhs {
+            Parsed::List(mut list) => {
+                Rc::make_mut(&mut list).insert(0, lhs);
+                Parsed::List(list)
+            },
+            rhs => &Parsed::list(vec![lhs]) + &rhs,
+        }
     }
 
 
@@ -359,109 +704,52 @@ impl Op {
     /// Consumes a Parsed value and returns a Quotation that places
     /// two instances of the consumed value back onto the stack.
     pub fn exec_dup(arg: Parsed) -> Parsed {
-        Parsed::Quotation(VecDeque::from(vec![arg.clone(), arg.clone()]))
+        Parsed::quotation(vec![arg.clone(), arg])
     }
 
     /// Takes two Parsed values and returns a Quotation that puts them back onto
     /// the sack in reverse order.
-    pub fn exec_swap(lhs: &Parsed, rhs: &Parsed) -> Parsed {
-        Parsed::Quotation(VecDeque::from(vec![rhs.clone(), lhs.clone()]))
+    pub fn exec_swap(lhs: Parsed, rhs: Parsed) -> Parsed {
+        Parsed::quotation(vec![rhs, lhs])
+    }
+
+    /// Checks whether the function takes its symbol modifier as written, rather than
+    /// the value bound to it.
+    pub fn quotes_modifier(&self) -> bool {
+        matches!(self, Op::AsSymbol | Op::Import | Op::Use)
+    }
+
+    /// Checks whether the quotation returned by the function holds values to push
+    /// as they are, rather than code to run.
+    pub fn pushes_values(&self) -> bool {
+        matches!(self, Op::Dup | Op::Swap)
     }
 
 
 
     //// CONTROL FUNCTION DEFINITIONS ////
 
-    pub fn exec_exec(arg: Parsed) -> Parsed {
-        match arg.coerce(&Type::Quotation) {
-            Parsed::Quotation(q) => Parsed::Quotation(q.clone()),
+    pub fn exec_exec(arg: Parsed, env: &Env) -> Parsed {
+        match as_code(arg, env) {
+            q @ Parsed::Quotation(..) => q,
             // TODO: Define
             _ => Parsed::Error(StackError::Undefined),
         }
     }
 
-    pub fn exec_if(arg: Parsed, c: Args) -> Parsed {
+    pub fn exec_if(arg: Parsed, c: Args, env: &Env) -> Parsed {
         match c {
             Args::Binary(then_quotation, else_quotation) => {
                 if arg == Parsed::Bool(true) {
-                    then_quotation.coerce(&Type::Quotation)
+                    as_code(then_quotation, env)
                 } else {
-                    else_quotation.coerce(&Type::Quotation)
+                    as_code(else_quotation, env)
                 }
             }
             _ => panic!("Invalid Closure count sent to if function"),
         }
     }
 
-    pub fn exec_times(arg: Parsed, c: Args) -> Parsed {
-        match c {
-            Args::Unary(quotation) => match arg {
-                Parsed::Num(Numeric::Integer(i)) => {
-                    let quotation = quotation.coerce(&Type::Quotation);
-                    return parse_to_quotation(
-                            format!(" {} 0 > if {{ {:?} exec {} 1 - times {:?} }} {{ }} ",
-                                    i, quotation,  i, quotation)
-                        );
-                }
-                //TODO: Stack error definition
-                _ => Parsed::Error(StackError::Undefined),
-            },
-            _ => panic!("Invalid Closure count sent to times function"),
-        }
-    }
-
-
-
-    //// HIGHER ORDER FUNCTION DEFINITIONS ////
-
-    /// Defines the map function.
-    ///
-    ///
-    /// {
-    pub fn exec_map(arg: Parsed, c: Args) -> Parsed {
-        match c {
-            Args::Unary(mut quotation) => {
-                quotation = quotation.coerce(&Type::Quotation);
-                return parse_to_quotation(
-                    format!(
-                        "{:?} length 0 > if {{  {:?} head {:?} exec {:?} tail map {:?} cons }} {{ [ ] }} ",
-                        arg, arg, quotation, arg, quotation
-                    )
-                );
-            }
-            _ => panic!("invalid closure count sent to map function"),
-        }
-    }
-
-    pub fn exec_each(arg: Parsed, c: Args) -> Parsed {
-        match c {
-            Args::Unary(mut quotation) => {
-                quotation = quotation.coerce(&Type::Quotation);
-                return parse_to_quotation(
-                    format!(
-                        " {:?} length 0 > if {{ {:?} head {:?} exec {:?} tail each {:?} }} {{ }} ",
-                        arg,  arg, quotation, arg, quotation)
-                );
-            }
-            _ => panic!("invalid closure count sent to each function"),
-        }
-    }
-
-
-    pub fn exec_foldl(lhs: &Parsed, rhs: &Parsed, c: Args) -> Parsed {
-        match c {
-            Args::Unary(quotation) => {
-                let quotation = quotation.coerce(&Type::Quotation);
-                return parse_to_quotation(
-                    format!(
-                        " {:?} length 0 > if {{ {:?} {:?} head {:?} exec {:?} tail swap foldl {:?} }} {{ {:?} }}  ",
-                        lhs,  rhs, lhs, quotation, lhs, quotation, rhs)
-                );
-            },
-            _ => panic!("invalid closure count sent to foldl function")
-        }
-    }
-
     /// Retrieves the Signature of a function, containing details about
     /// argument and return constraints.
     pub fn get_signature(&self) -> Signature {
@@ -475,6 +763,27 @@ impl Op {
             Op::Mod => Self::get_mod_sig(),
             Op::Add | Op::Sub | Op::Mul | Op::Div => Self::get_arithmetic_sig(),
             Op::IntDiv => Self::get_arithmetic_sig(),
+            Op::Overflow => Self::get_overflow_sig(),
+            Op::SetOverflow => Self::get_set_overflow_sig(),
+            Op::DepthLimit => Self::get_depth_limit_sig(),
+            Op::SetDepthLimit => Self::get_set_depth_limit_sig(),
+            Op::Numerator | Op::Denominator => Self::get_fraction_part_sig(),
+            Op::ToFloat => Self::get_to_float_sig(),
+            Op::Abs => Self::get_abs_sig(),
+            Op::Min | Op::Max | Op::Pow => Self::get_arithmetic_sig(),
+            Op::Sqrt | Op::Exp | Op::Ln | Op::Log10 => Self::get_real_sig(),
+            Op::Sin | Op::Cos | Op::Tan | Op::Asin | Op::Acos | Op::Atan => Self::get_real_sig(),
+            Op::Floor | Op::Ceil | Op::Round | Op::Truncate => Self::get_rounding_sig(),
+            Op::Pi | Op::E => Self::get_constant_sig(),
+            Op::BitAnd | Op::BitOr | Op::BitXor => Self::get_binary_integer_sig(),
+            Op::ShiftLeft | Op::ShiftRight | Op::Gcd | Op::Lcm => Self::get_binary_integer_sig(),
+            Op::BitNot | Op::PopCount | Op::ISqrt => Self::get_unary_integer_sig(),
+            Op::ToHex | Op::ToBinary | Op::ToOctal => Self::get_to_radix_sig(),
+            Op::RandInt => Self::get_binary_integer_sig(),
+            Op::RandFloat => Self::get_constant_sig(),
+            Op::Shuffle => Self::get_shuffle_sig(),
+            Op::Sample => Self::get_sample_sig(),
+            Op::Seed => Self::get_seed_sig(),
             Op::LT | Op::GT => Self::get_ord_sig(),
             Op::EQ => Self::get_eq_sig(),
             Op::And | Op::Or => Self::get_and_or_sig(),
@@ -492,6 +801,9 @@ impl Op {
             Op::Loop => Self::get_loop_sig(),
             Op::Times => Self::get_times_sig(),
             Op::Exec => Self::get_exec_sig(),
+            Op::Global => Self::get_global_sig(),
+            Op::Locals(_) => Self::get_locals_sig(),
+            Op::Import | Op::Use => Self::get_import_sig(),
             Op::Assign => Self::get_assign_sig(),
             Op::AssignFunc => Self::get_assign_func_sig(),
             Op::AsSymbol => Self::get_as_symbol_sig(),
@@ -500,22 +812,55 @@ impl Op {
             Op::Swap => Self::get_swap_sig(),
             Op::Pop => Self::get_pop_sig(),
             Op::Error => Self::get_err_sig(),
+            Op::Try | Op::Ensure => Self::get_try_sig(),
+            Op::Throw => Self::get_throw_sig(),
+            Op::ErrorMessage => Self::get_error_message_sig(),
+            Op::ErrorPayload => Self::get_error_payload_sig(),
+            Op::Just => Self::get_just_sig(),
+            Op::Nothing => Self::get_nothing_sig(),
+            Op::Ok | Op::Failure => Self::get_ok_sig(),
+            Op::UnwrapOr => Self::get_unwrap_or_sig(),
+            Op::Case => Self::get_case_sig(),
+            Op::SafeParseInt | Op::SafeParseFloat => Self::get_safe_parse_sig(),
+            Op::SafeHead | Op::SafeTail => Self::get_safe_list_sig(),
         }
     }
 
     fn get_err_sig() -> Signature {
         let mut sig = nullary(Constraint::Error);
-        sig.modifiers = Params::Unary(Constraint::String);
+        sig.modifiers = Params::Unary(Constraint::Any);
         sig
     }
 
     fn exec_err(mods: Args) -> Parsed {
-        if let Args::Unary(Parsed::String(err)) = mods {
-            return Parsed::Error(StackError::UserDefined(err.clone()))
+        if let Args::Unary(payload) = mods {
+            return Parsed::Error(StackError::UserDefined(Box::new(payload)))
         }
         panic!("bug: invalid modifier sent to exec_err.")
     }
 
+    /// Raises an error with the value as its payload, or raises a caught error again.
+    fn exec_throw(arg: Parsed) -> Parsed {
+        match arg {
+            Parsed::Caught(err) => Parsed::Error(err),
+            payload => Parsed::Error(StackError::UserDefined(Box::new(payload))),
+        }
+    }
+
+    fn exec_error_message(arg: Parsed) -> Parsed {
+        match arg {
+            Parsed::Caught(err) => Parsed::string(err.message()),
+            _ => panic!("bug: invalid argument sent to errorMessage. Check constraints."),
+        }
+    }
+
+    fn exec_error_payload(arg: Parsed) -> Parsed {
+        match arg {
+            Parsed::Caught(err) => err.payload(),
+            _ => panic!("bug: invalid argument sent to errorPayload. Check constraints."),
+        }
+    }
+
     //////////////////////////////// SIGNATURE DEFINITIONS //////////////////////////////////////
 
     //// VOID /////
@@ -557,6 +902,70 @@ impl Op {
         homogenous_binary(Constraint::Num, Constraint::Num)
     }
 
+    fn get_overflow_sig() -> Signature {
+        nullary(Constraint::String)
+    }
+
+    fn get_set_overflow_sig() -> Signature {
+        unary(Constraint::String, Constraint::Void)
+    }
+
+    fn get_depth_limit_sig() -> Signature {
+        nullary(Constraint::Integer)
+    }
+
+    fn get_set_depth_limit_sig() -> Signature {
+        unary(Constraint::Integer, Constraint::Void)
+    }
+
+    fn get_fraction_part_sig() -> Signature {
+        unary(Constraint::Exact, Constraint::Integer)
+    }
+
+    fn get_to_float_sig() -> Signature {
+        unary(Constraint::Num, Constraint::Float)
+    }
+
+    fn get_abs_sig() -> Signature {
+        unary(Constraint::Num, Constraint::Num)
+    }
+
+    fn get_real_sig() -> Signature {
+        unary(Constraint::Num, Constraint::Float)
+    }
+
+    fn get_rounding_sig() -> Signature {
+        unary(Constraint::Num, Constraint::Integer)
+    }
+
+    fn get_constant_sig() -> Signature {
+        nullary(Constraint::Float)
+    }
+
+    fn get_binary_integer_sig() -> Signature {
+        homogenous_binary(Constraint::Integer, Constraint::Integer)
+    }
+
+    fn get_unary_integer_sig() -> Signature {
+        unary(Constraint::Integer, Constraint::Integer)
+    }
+
+    fn get_to_radix_sig() -> Signature {
+        unary(Constraint::Integer, Constraint::String)
+    }
+
+    fn get_shuffle_sig() -> Signature {
+        unary(Constraint::List, Constraint::List)
+    }
+
+    fn get_sample_sig() -> Signature {
+        heterogeneous_binary(Constraint::List, Constraint::Integer, Constraint::List)
+    }
+
+    fn get_seed_sig() -> Signature {
+        unary(Constraint::Integer, Constraint::Void)
+    }
+
     fn get_ord_sig() -> Signature {
         homogenous_binary(Constraint::Ord, Constraint::Bool)
     }
@@ -614,7 +1023,7 @@ impl Op {
     }
 
     fn get_map_sig() -> Signature {
-        let mut sig = unary(Constraint::List, Constraint::Quotation);
+        let mut sig = unary(Constraint::Functor, Constraint::Quotation);
         sig.modifiers = Params::Unary(Constraint::Executable);
         sig
     }
@@ -653,6 +1062,78 @@ impl Op {
         unary(Constraint::Executable, Constraint::Executable)
     }
 
+    pub fn get_global_sig() -> Signature {
+        let mut sig = nullary(Constraint::Any);
+        sig.modifiers = Params::Unary(Constraint::Executable);
+        sig
+    }
+
+    /// The values bound by a header are popped by the interpreter, as there may be
+    /// any number of them.
+    pub fn get_locals_sig() -> Signature {
+        nullary(Constraint::Void)
+    }
+
+    //// MODULES ////
+
+    pub fn get_import_sig() -> Signature {
+        let mut sig = nullary(Constraint::Void);
+        sig.modifiers = Params::Unary(Constraint::Symbol);
+        sig
+    }
+
+    //// ERRORS ////
+
+    pub fn get_try_sig() -> Signature {
+        let mut sig = nullary(Constraint::Any);
+        sig.modifiers = Params::Binary(Constraint::Executable, Constraint::Executable);
+        sig
+    }
+
+    pub fn get_throw_sig() -> Signature {
+        unary(Constraint::Any, Constraint::Error)
+    }
+
+    pub fn get_error_message_sig() -> Signature {
+        unary(Constraint::Error, Constraint::String)
+    }
+
+    pub fn get_error_payload_sig() -> Signature {
+        unary(Constraint::Error, Constraint::Any)
+    }
+
+    //// MAYBE AND RESULT ////
+
+    pub fn get_just_sig() -> Signature {
+        unary(Constraint::Any, Constraint::Maybe)
+    }
+
+    pub fn get_nothing_sig() -> Signature {
+        nullary(Constraint::Maybe)
+    }
+
+    pub fn get_ok_sig() -> Signature {
+        unary(Constraint::Any, Constraint::Result)
+    }
+
+    pub fn get_unwrap_or_sig() -> Signature {
+        heterogeneous_binary(Constraint::Fallible, Constraint::Any, Constraint::Any)
+    }
+
+    pub fn get_case_sig() -> Signature {
+        let mut sig = unary(Constraint::Fallible, Constraint::Any);
+        sig.modifiers = Params::Binary(Constraint::Executable, Constraint::Executable);
+        sig
+    }
+
+    pub fn get_safe_parse_sig() -> Signature {
+        unary(Constraint::String, Constraint::Result)
+    }
+
+    pub fn get_safe_list_sig() -> Signature {
+        unary(Constraint::List, Constraint::Maybe)
+    }
+
     //// ASSIGNMENT ////
 
     pub fn get_assign_sig() -> Signature {
@@ -716,6 +1197,51 @@ impl Display for Op {
             Op::Mul => write!(f, "*"),
             Op::Div => write!(f, "/"),
             Op::IntDiv => write!(f, "div"),
+            Op::Overflow => write!(f, "overflow"),
+            Op::SetOverflow => write!(f, "setOverflow"),
+            Op::DepthLimit => write!(f, "depthLimit"),
+            Op::SetDepthLimit => write!(f, "setDepthLimit"),
+            Op::Numerator => write!(f, "numerator"),
+            Op::Denominator => write!(f, "denominator"),
+            Op::ToFloat => write!(f, "toFloat"),
+            Op::Abs => write!(f, "abs"),
+            Op::Min => write!(f, "min"),
+            Op::Max => write!(f, "max"),
+            Op::Pow => write!(f, "pow"),
+            Op::Sqrt => write!(f, "sqrt"),
+            Op::Exp => write!(f, "exp"),
+            Op::Ln => write!(f, "ln"),
+            Op::Log10 => write!(f, "log10"),
+            Op::Sin => write!(f, "sin"),
+            Op::Cos => write!(f, "cos"),
+            Op::Tan => write!(f, "tan"),
+            Op::Asin => write!(f, "asin"),
+            Op::Acos => write!(f, "acos"),
+            Op::Atan => write!(f, "atan"),
+            Op::Floor => write!(f, "floor"),
+            Op::Ceil => write!(f, "ceil"),
+            Op::Round => write!(f, "round"),
+            Op::Truncate => write!(f, "truncate"),
+            Op::Pi => write!(f, "pi"),
+            Op::E => write!(f, "e"),
+            Op::BitAnd => write!(f, "band"),
+            Op::BitOr => write!(f, "bor"),
+            Op::BitXor => write!(f, "bxor"),
+            Op::BitNot => write!(f, "bnot"),
+            Op::ShiftLeft => write!(f, "shl"),
+            Op::ShiftRight => write!(f, "shr"),
+            Op::PopCount => write!(f, "popcount"),
+            Op::Gcd => write!(f, "gcd"),
+            Op::Lcm => write!(f, "lcm"),
+            Op::ISqrt => write!(f, "isqrt"),
+            Op::ToHex => write!(f, "toHex"),
+            Op::ToBinary => write!(f, "toBinary"),
+            Op::ToOctal => write!(f, "toOctal"),
+            Op::RandInt => write!(f, "randInt"),
+            Op::RandFloat => write!(f, "randFloat"),
+            Op::Shuffle => write!(f, "shuffle"),
+            Op::Sample => write!(f, "sample"),
+            Op::Seed => write!(f, "seed"),
             Op::LT => write!(f, "<"),
             Op::GT => write!(f, ">"),
             Op::EQ => write!(f, "=="),
@@ -735,6 +1261,13 @@ impl Display for Op {
             Op::Loop => write!(f, "loop"),
             Op::Times => write!(f, "times"),
             Op::Exec => write!(f, "exec"),
+            Op::Global => write!(f, "global"),
+            Op::Import => write!(f, "import"),
+            Op::Use => write!(f, "use"),
+            Op::Locals(names) => {
+                names.iter().try_for_each(|name| write!(f, "{} ", name))?;
+                write!(f, "->")
+            },
             Op::Assign => write!(f, ":="),
             Op::AssignFunc => write!(f, "fun"),
             Op::AsSymbol => write!(f, "'"),
@@ -743,6 +1276,21 @@ impl Display for Op {
             Op::Swap => write!(f, "swap"),
             Op::Pop => write!(f, "pop"),
             Op::Error => write!(f, "err"),
+            Op::Try => write!(f, "try"),
+            Op::Ensure => write!(f, "ensure"),
+            Op::Throw => write!(f, "throw"),
+            Op::ErrorMessage => write!(f, "errorMessage"),
+            Op::ErrorPayload => write!(f, "errorPayload"),
+            Op::Just => write!(f, "just"),
+            Op::Nothing => write!(f, "nothing"),
+            Op::Ok => write!(f, "ok"),
+            Op::Failure => write!(f, "failure"),
+            Op::UnwrapOr => write!(f, "unwrapOr"),
+            Op::Case => write!(f, "case"),
+            Op::SafeParseInt => write!(f, "safeParseInteger"),
+            Op::SafeParseFloat => write!(f, "safeParseFloat"),
+            Op::SafeHead => write!(f, "safeHead"),
+            Op::SafeTail => write!(f, "safeTail"),
         }
     }
 }
@@ -765,6 +1313,51 @@ impl FromStr for Op {
             "*" => Ok(Op::Mul),
             "/" => Ok(Op::Div),
             "div" => Ok(Op::IntDiv),
+            "overflow" => Ok(Op::Overflow),
+            "setOverflow" => Ok(Op::SetOverflow),
+            "depthLimit" => Ok(Op::DepthLimit),
+            "setDepthLimit" => Ok(Op::SetDepthLimit),
+            "numerator" => Ok(Op::Numerator),
+            "denominator" => Ok(Op::Denominator),
+            "toFloat" => Ok(Op::ToFloat),
+            "abs" => Ok(Op::Abs),
+            "min" => Ok(Op::Min),
+            "max" => Ok(Op::Max),
+            "pow" => Ok(Op::Pow),
+            "sqrt" => Ok(Op::Sqrt),
+            "exp" => Ok(Op::Exp),
+            "ln" => Ok(Op::Ln),
+            "log10" => Ok(Op::Log10),
+            "sin" => Ok(Op::Sin),
+            "cos" => Ok(Op::Cos),
+            "tan" => Ok(Op::Tan),
+            "asin" => Ok(Op::Asin),
+            "acos" => Ok(Op::Acos),
+            "atan" => Ok(Op::Atan),
+            "floor" => Ok(Op::Floor),
+            "ceil" => Ok(Op::Ceil),
+            "round" => Ok(Op::Round),
+            "truncate" => Ok(Op::Truncate),
+            "pi" => Ok(Op::Pi),
+            "e" => Ok(Op::E),
+            "band" => Ok(Op::BitAnd),
+            "bor" => Ok(Op::BitOr),
+            "bxor" => Ok(Op::BitXor),
+            "bnot" => Ok(Op::BitNot),
+            "shl" => Ok(Op::ShiftLeft),
+            "shr" => Ok(Op::ShiftRight),
+            "popcount" => Ok(Op::PopCount),
+            "gcd" => Ok(Op::Gcd),
+            "lcm" => Ok(Op::Lcm),
+            "isqrt" => Ok(Op::ISqrt),
+            "toHex" => Ok(Op::ToHex),
+            "toBinary" => Ok(Op::ToBinary),
+            "toOctal" => Ok(Op::ToOctal),
+            "randInt" => Ok(Op::RandInt),
+            "randFloat" => Ok(Op::RandFloat),
+            "shuffle" => Ok(Op::Shuffle),
+            "sample" => Ok(Op::Sample),
+            "seed" => Ok(Op::Seed),
             "<" => Ok(Op::LT),
             ">" => Ok(Op::GT),
             "==" => Ok(Op::EQ),
@@ -784,6 +1377,9 @@ impl FromStr for Op {
             "loop" => Ok(Op::Loop),
             "times" => Ok(Op::Times),
             "exec" => Ok(Op::Exec),
+            "global" => Ok(Op::Global),
+            "import" => Ok(Op::Import),
+            "use" => Ok(Op::Use),
             ":=" => Ok(Op::Assign),
             "fun" => Ok(Op::AssignFunc),
             "'" => Ok(Op::AsSymbol),
@@ -793,6 +1389,21 @@ impl FromStr for Op {
             "dup" => Ok(Op::Dup),
             "()" => Ok(Op::Void),
             "err" => Ok(Op::Error),
+            "try" => Ok(Op::Try),
+            "ensure" => Ok(Op::Ensure),
+            "throw" => Ok(Op::Throw),
+            "errorMessage" => Ok(Op::ErrorMessage),
+            "errorPayload" => Ok(Op::ErrorPayload),
+            "just" => Ok(Op::Just),
+            "nothing" => Ok(Op::Nothing),
+            "ok" => Ok(Op::Ok),
+            "failure" => Ok(Op::Failure),
+            "unwrapOr" => Ok(Op::UnwrapOr),
+            "case" => Ok(Op::Case),
+            "safeParseInteger" => Ok(Op::SafeParseInt),
+            "safeParseFloat" => Ok(Op::SafeParseFloat),
+            "safeHead" => Ok(Op::SafeHead),
+            "safeTail" => Ok(Op::SafeTail),
             _ => Err(format!("unknown operation: {}", s)),
         }
     }
@@ -0,0 +1,159 @@
+/////////////////////////// PACKAGE ///////////////////////////////////////////////////////////////
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Name of the manifest file that makes a directory a package.
+pub const MANIFEST: &str = "bprog.pkg";
+
+/// Environment variable holding directories to search for modules and packages in,
+/// separated like the directories of `PATH`.
+pub const SEARCH_PATH_VAR: &str = "BPROG_PATH";
+
+/// A directory of modules shared as a unit, described by its manifest. Packages are
+/// installed by placing their directory, named after the package, in a directory of
+/// the search path, and the module `name` of the package is imported as `name`, while
+/// its other modules are imported as `name/module`.
+///
+/// The manifest has a `key = value` setting on each line, and may have `--` comments:
+///
+/// ```text
+/// name = geometry
+/// version = 1.2.0
+/// dependencies = vectors shapes
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    /// Names of the packages the modules of this package may import.
+    pub dependencies: Vec<String>,
+    /// Directory holding the manifest and the modules of the package.
+    pub root: PathBuf,
+}
+
+impl Package {
+    /// Parses the manifest of a package found in the directory `root`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bprog::package::Package;
+    ///
+    /// let package = Package::parse("geometry".as_ref(), "name = geometry -- shapes\nversion = 1.0\ndependencies = vectors").unwrap();
+    ///
+    /// assert_eq!("geometry", package.name);
+    /// assert_eq!(vec!["vectors".to_string()], package.dependencies);
+    /// assert!(Package::parse("geometry".as_ref(), "version = 1.0").is_err());
+    /// ```
+    pub fn parse(root: &Path, text: &str) -> Result<Package, String> {
+        let mut package = Package { name: String::new(), version: String::new(), dependencies: vec![], root: root.into() };
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split("--").next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("{}:{}: expected `key = value`", MANIFEST, i + 1));
+            };
+            let value = value.trim().to_string();
+            match key.trim() {
+                "name" => package.name = value,
+                "version" => package.version = value,
+                "dependencies" => package.dependencies = value.split_whitespace().map(String::from).collect(),
+                key => return Err(format!("{}:{}: unknown setting `{}`", MANIFEST, i + 1, key)),
+            }
+        }
+        if package.name.is_empty() {
+            return Err(format!("{} in {} does not name the package", MANIFEST, root.display()));
+        }
+        Ok(package)
+    }
+
+    /// Reads the manifest of the package in the directory `root`.
+    pub fn read(root: &Path) -> Result<Package, String> {
+        let path = root.join(MANIFEST);
+        let text = fs::read_to_string(&path)
+            .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+        Package::parse(root, &text)
+    }
+
+    /// Finds the package installed under a name, in the first directory of the search
+    /// path that has one. Returns None if no package of the name is installed.
+    pub fn find(name: &str, search_path: &[PathBuf]) -> Option<Result<Package, String>> {
+        let root = search_path.iter()
+            .map(|dir| dir.join(name))
+            .find(|root| root.join(MANIFEST).is_file())?;
+        Some(Package::read(&root).and_then(|package| match package.name == name {
+            true => Ok(package),
+            false => Err(format!("package in {} is named {}, not {}", root.display(), package.name, name)),
+        }))
+    }
+
+    /// Returns the packages installed in the directories of the search path, in the
+    /// order they are searched. A package is shadowed by any package of the same name
+    /// before it.
+    pub fn installed(search_path: &[PathBuf]) -> Vec<Result<Package, String>> {
+        let mut packages = vec![];
+        for dir in search_path {
+            let Ok(entries) = fs::read_dir(dir) else { continue };
+            let mut roots: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|root| root.join(MANIFEST).is_file())
+                .collect();
+            roots.sort();
+            packages.extend(roots.iter().map(|root| Package::read(root)));
+        }
+        packages
+    }
+
+    /// Returns the package a file belongs to, which is the closest directory around it
+    /// with a manifest.
+    pub fn containing(file: &Path) -> Option<Package> {
+        file.ancestors().skip(1)
+            .find(|dir| dir.join(MANIFEST).is_file())
+            .and_then(|root| Package::read(root).ok())
+    }
+
+    /// Returns the file of a module of the package, given its path within the package,
+    /// or the module named after the package for an empty path. Paths leaving the
+    /// directory of the package are rejected.
+    pub fn module(&self, path: &str) -> Result<PathBuf, String> {
+        if !is_below(path) {
+            return Err(format!("module path {} leaves package {}", path, self.name));
+        }
+        let module = if path.is_empty() { &self.name } else { path };
+        Ok(self.root.join(format!("{}.bprog", module)))
+    }
+
+    /// Returns whether a file is in the directory of the package.
+    pub fn holds(&self, file: &Path) -> bool {
+        match (file.canonicalize(), self.root.canonicalize()) {
+            (Ok(file), Ok(root)) => file.starts_with(root),
+            _ => false,
+        }
+    }
+}
+
+/// Returns whether a relative path only names files below the directory it is joined
+/// to, having no `..`, root or prefix components.
+///
+/// # Examples
+///
+/// ```
+/// use bprog::package::is_below;
+///
+/// assert!(is_below("shapes/circle"));
+/// assert!(!is_below("shapes/../vectors"));
+/// assert!(!is_below("/etc/passwd"));
+/// ```
+pub fn is_below(path: &str) -> bool {
+    Path::new(path).components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Returns the directories of the search path set in the environment.
+pub fn env_search_path() -> Vec<PathBuf> {
+    std::env::var_os(SEARCH_PATH_VAR)
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use crate::span::Span;
+
+#[derive(Clone, Debug, PartialEq)]
+/// ParseError represents problems found while reading program text, before any
+/// of it is executed. Every variant carries the span of the offending token.
+pub enum ParseError {
+    // Bracket errors
+    UnclosedBracket(String, Span),
+    UnmatchedBracket(String, Span),
+    MismatchedBracket { open: String, open_span: Span, close: String, span: Span },
+
+    // Literal errors
+    UnterminatedString(Span),
+    UnterminatedComment(Span),
+    InvalidEscape(String, Span),
+
+    // All problems found in a single pass, ordered by position in the source.
+    Multiple(Vec<ParseError>),
+}
+
+impl ParseError {
+    /// Combines the errors found during a pass into a single error, or None if
+    /// no errors were found.
+    pub fn from_errors(mut errors: Vec<ParseError>) -> Option<ParseError> {
+        match errors.len() {
+            0 => None,
+            1 => errors.pop(),
+            _ => Some(ParseError::Multiple(errors)),
+        }
+    }
+
+    /// Returns the position of the token that caused the error.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            ParseError::UnclosedBracket(_, span) |
+            ParseError::UnmatchedBracket(_, span) |
+            ParseError::MismatchedBracket { span, .. } |
+            ParseError::UnterminatedString(span) |
+            ParseError::UnterminatedComment(span) |
+            ParseError::InvalidEscape(_, span) => Some(span),
+            ParseError::Multiple(_) => None,
+        }
+    }
+
+    /// Returns every individual error, flattening Multiple.
+    pub fn errors(&self) -> Vec<&ParseError> {
+        match self {
+            ParseError::Multiple(errors) => errors.iter().flat_map(|e| e.errors()).collect(),
+            other => vec![other],
+        }
+    }
+}
+
+/// Implements Display for ParseError, writing the location of the error and the
+/// source line followed by "err: <specific error message>".
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnclosedBracket(open, span) => write!(f, "{}", span.report(&format!(
+                "\x1b[31merr: {} is never closed, expected a matching {}\x1b[0m", open, closing(open)))),
+            ParseError::UnmatchedBracket(close, span) => write!(f, "{}", span.report(&format!(
+                "\x1b[31merr: {} has no matching {}\x1b[0m", close, opening(close)))),
+            ParseError::MismatchedBracket { open, open_span, close, span } => write!(f, "{}", span.report(&format!(
+                "\x1b[31merr: {} does not match the {} opened at {}, expected {}\x1b[0m",
+                close, open, open_span, closing(open)))),
+            ParseError::UnterminatedString(span) => write!(f, "{}", span.report(
+                "\x1b[31merr: failed to find terminating \" token for string while parsing input.\x1b[0m")),
+            ParseError::UnterminatedComment(span) => write!(f, "{}", span.report(
+                "\x1b[31merr: failed to find terminating -} for block comment while parsing input.\x1b[0m")),
+            ParseError::InvalidEscape(escape, span) => write!(f, "{}", span.report(&format!(
+                "\x1b[31merr: invalid escape sequence {} in string\x1b[0m", escape))),
+            ParseError::Multiple(errors) => {
+                let mut iter = errors.iter();
+                if let Some(first) = iter.next() {
+                    write!(f, "{}", first)?;
+                    for error in iter {
+                        write!(f, "\n\n{}", error)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the closing bracket for an opening bracket.
+pub fn closing(open: &str) -> &str {
+    match open {
+        "{" => "}",
+        "[" => "]",
+        _ => "",
+    }
+}
+
+/// Returns the opening bracket for a closing bracket.
+pub fn opening(close: &str) -> &str {
+    match close {
+        "}" => "{",
+        "]" => "[",
+        _ => "",
+    }
+}
@@ -3,8 +3,12 @@ use std::collections::VecDeque;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::{Add, BitAnd, BitOr, Div, Mul, Neg, Sub};
-use crate::numeric::Numeric;
+use std::rc::Rc;
+use crate::name::Name;
+use crate::numeric::{Arithmetic, Numeric, OverflowPolicy};
 use crate::op::Op;
+use crate::scope::Scope;
+use crate::span::Span;
 use crate::stack_error::StackError;
 use crate::types::{numeric_coercion, Type};
 
@@ -13,16 +17,27 @@ use crate::types::{numeric_coercion, Type};
 #[derive(Clone)]
 /// enumeration of stack values, allowing a container to hold
 /// arbitrary types.
+///
+/// Strings, quotations and lists are shared by the copies of a value, and only copied
+/// when a shared one is changed, so copying a value costs the same whatever its size.
 pub enum Parsed {
     Void,
     Num(Numeric),
-    String(String),
+    String(Rc<String>),
     Bool(bool),
-    Quotation(VecDeque<Parsed>),
-    Symbol(String),
-    List(Vec<Parsed>),
+    /// A quotation, with the scope it was pushed in, if that was not the global one.
+    Quotation(Rc<VecDeque<Parsed>>, Option<Rc<Scope>>),
+    Symbol(Name, Span),
+    List(Rc<Vec<Parsed>>),
     Error(StackError),
-    Function(Op),
+    /// An error caught by `try`, held as a value to inspect or throw again.
+    Caught(StackError),
+    /// A value that may be missing, `Just` the value or `Nothing`.
+    Maybe(Option<Rc<Parsed>>),
+    /// The outcome of an operation that may fail, `Ok` with its value or a `Failure`
+    /// with the reason it failed.
+    Result(Result<Rc<Parsed>, Rc<Parsed>>),
+    Function(Op, Span),
 }
 
 impl Neg for Parsed {
@@ -38,7 +53,7 @@ impl Neg for Parsed {
 }
 
 /// Implements Add for StackTokens, with varying behaviour depending on the type.
-impl<'a, 'b> Add<&'b Parsed> for &'a Parsed { //impl<'a, 'b> Add<&'b Numeric> for &'a Numeric
+impl<'b> Add<&'b Parsed> for &Parsed { //impl<'a, 'b> Add<&'b Numeric> for &'a Numeric
     type Output = Parsed;
 
     fn add(self, rhs: &'b Parsed) -> Self::Output {
@@ -47,19 +62,13 @@ impl<'a, 'b> Add<&'b Parsed> for &'a Parsed { //impl<'a, 'b> Add<&'b Numeric> fo
                 Parsed::Num(n + n2)
             },
             (Parsed::String(s), Parsed::String(s2)) => {
-                Parsed::String(s.clone().add(&s2))
+                Parsed::string(s.to_string().add(s2))
             },
             (Parsed::List(l1), Parsed::List(l2)) => {
-                let mut l1c = l1.clone();
-                let mut l2c = l2.clone();
-                l1c.append(&mut l2c);
-                Parsed::List(l1c)
+                Parsed::list(l1.iter().chain(l2.iter()).cloned().collect())
             },
             (elem, Parsed::List(old_list)) => {
-                let mut new_list:Vec<Parsed> = Vec::new();
-                new_list.push(elem.clone());
-                new_list.append(&mut old_list.clone());
-                Parsed::List(new_list)
+                Parsed::list(std::iter::once(elem).chain(old_list.iter()).cloned().collect())
             }
             /*
             (_, Parsed::Num(_)) => Parsed::Error(StackError::InvalidLeft),
@@ -74,7 +83,7 @@ impl<'a, 'b> Add<&'b Parsed> for &'a Parsed { //impl<'a, 'b> Add<&'b Numeric> fo
 }
 
 /// Implements Sub for StackTokens, with varying behaviour depending on the type.
-impl<'a, 'b> Sub<&'b Parsed> for &'a Parsed { //impl<'a, 'b> Add<&'b Numeric> for &'a Numeric
+impl<'b> Sub<&'b Parsed> for &Parsed { //impl<'a, 'b> Add<&'b Numeric> for &'a Numeric
 type Output = Parsed;
 
     fn sub(self, rhs: &'b Parsed) -> Self::Output {
@@ -86,7 +95,7 @@ type Output = Parsed;
 }
 
 /// Implements Mul for StackTokens, with varying behaviour depending on the type.
-impl<'a, 'b> Mul<&'b Parsed> for &'a Parsed { //impl<'a, 'b> Add<&'b Numeric> for &'a Numeric
+impl<'b> Mul<&'b Parsed> for &Parsed { //impl<'a, 'b> Add<&'b Numeric> for &'a Numeric
 type Output = Parsed;
 
     fn mul(self, rhs: &'b Parsed) -> Self::Output {
@@ -99,12 +108,13 @@ type Output = Parsed;
 }
 
 /// Implements Div for StackTokens, with varying behaviour depending on the type.
-impl<'a, 'b> Div<&'b Parsed> for &'a Parsed { //impl<'a, 'b> Add<&'b Numeric> for &'a Numeric
+/// Integers divided by integers are truncated, see Numeric.
+impl<'b> Div<&'b Parsed> for &Parsed { //impl<'a, 'b> Add<&'b Numeric> for &'a Numeric
 type Output = Parsed;
 
     fn div(self, rhs: &'b Parsed) -> Self::Output {
-        match (self.coerce(&Type::Float), rhs.coerce(&Type::Float)) {
-            (Parsed::Num(v1), Parsed::Num(v2)) => Parsed::Num(&v1 / &v2),
+        match (self, rhs) {
+            (Parsed::Num(v1), Parsed::Num(v2)) => Parsed::Num(v1 / v2),
             (_, _) => panic!("bug: subtraction used with inappropriate types.")
         }
     }
@@ -112,6 +122,30 @@ type Output = Parsed;
 
 // Parsed methods
 impl Parsed {
+    pub fn string(s: impl Into<String>) -> Parsed {
+        Parsed::String(Rc::new(s.into()))
+    }
+
+    pub fn list(items: Vec<Parsed>) -> Parsed {
+        Parsed::List(Rc::new(items))
+    }
+
+    pub fn quotation(items: impl Into<VecDeque<Parsed>>) -> Parsed {
+        Parsed::Quotation(Rc::new(items.into()), None)
+    }
+
+    pub fn just(value: Parsed) -> Parsed {
+        Parsed::Maybe(Some(Rc::new(value)))
+    }
+
+    pub fn ok(value: Parsed) -> Parsed {
+        Parsed::Result(Ok(Rc::new(value)))
+    }
+
+    pub fn failure(reason: Parsed) -> Parsed {
+        Parsed::Result(Err(Rc::new(reason)))
+    }
+
     /// Defines what can be StackToken variants can interpreted as true,
     /// and under which conditions they are considered true.
     fn is_true(&self) -> bool {
@@ -125,6 +159,33 @@ impl Parsed {
         }
     }
 
+    /// Applies an arithmetic operation to two numbers, handling integer results that
+    /// do not fit in an i128 as the policy says. Failed operations give an Error, so
+    /// that the interpreter reports them.
+    pub fn arithmetic(&self, op: Arithmetic, rhs: &Parsed, policy: OverflowPolicy) -> Parsed {
+        match (self, rhs) {
+            (Parsed::Num(v1), Parsed::Num(v2)) => Parsed::from_numeric(v1.arithmetic(op, v2, policy)),
+            (_, _) => panic!("bug: arithmetic used with inappropriate types.")
+        }
+    }
+
+    /// Wraps the result of a numeric operation, turning a failed operation into an
+    /// Error so that the interpreter reports it.
+    pub fn from_numeric(n: Numeric) -> Parsed {
+        match n {
+            Numeric::NumError(err) => Parsed::Error(err),
+            result => Parsed::Num(result),
+        }
+    }
+
+    /// Returns the source position of symbols and functions read by the parser.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Parsed::Symbol(_, span) | Parsed::Function(_, span) => Some(span),
+            _ => None,
+        }
+    }
+
     pub fn get_type(&self) -> Type {
         match self {
             Parsed::Void => {
@@ -132,25 +193,28 @@ impl Parsed {
             },
             Parsed::Num(numerical) => {
                 match numerical {
-                    Numeric::Integer(_) => Type::Integer,
+                    Numeric::Integer(_) | Numeric::BigInteger(_) => Type::Integer,
+                    Numeric::Rational(_) => Type::Rational,
                     Numeric::Float(_) => Type::Float,
                     Numeric::NumError(_) => Type::Error
                 }
             }
             Parsed::String(_) => Type::String,
             Parsed::Bool(_) => Type::Bool,
-            Parsed::Quotation(_) => Type::Quotation,
-            Parsed::Symbol(_) => Type::Symbol,
+            Parsed::Quotation(..) => Type::Quotation,
+            Parsed::Symbol(_, _) => Type::Symbol,
             Parsed::List(_) => Type::List,
-            Parsed::Error(_) => Type::Error,
-            Parsed::Function(op) => Type::Function(op.get_signature())
+            Parsed::Error(_) | Parsed::Caught(_) => Type::Error,
+            Parsed::Maybe(_) => Type::Maybe,
+            Parsed::Result(_) => Type::Result,
+            Parsed::Function(op, _) => Type::Function(op.get_signature())
         }
     }
 
     pub fn size (&self) -> Parsed {
         match self {
             Parsed::String(s) => Parsed::Num(Numeric::Integer(s.len() as i128)),
-            Parsed::Quotation(b) => Parsed::Num(Numeric::Integer(b.len() as i128)),
+            Parsed::Quotation(b, _) => Parsed::Num(Numeric::Integer(b.len() as i128)),
             Parsed::List(l) => Parsed::Num(Numeric::Integer(l.len() as i128)),
             _ => panic!("bug: size used with inappropriate type.")
         }
@@ -160,10 +224,10 @@ impl Parsed {
     pub fn get_contents(&self) -> Option<Vec<Parsed>>{
         match self {
             Parsed::List(l) => {
-                Some(l.clone())
+                Some(l.to_vec())
             },
-            Parsed::Quotation(q) => {
-                Some(Vec::from(q.clone()))
+            Parsed::Quotation(q, _) => {
+                Some(q.iter().cloned().collect())
             }
             _ => None
         }
@@ -176,12 +240,14 @@ impl Parsed {
             Type::String => self.to_string(),
             Type::List => self.to_list(),
             Type::Integer => self.to_integer(),
+            Type::Rational => self.to_rational(),
             Type::Float => self.to_float(),
             Type::Bool => self.to_bool(),
             Type::Quotation => self.to_quotation(),
             Type::Error => self.to_string(),
             Type::Symbol => self.to_symbol(),
             Type::Function(_) => self.to_function(),
+            Type::Maybe | Type::Result => None,
         };
         if let Some(p) = res {
             p
@@ -226,14 +292,24 @@ impl Parsed {
         }
     }
 
+    fn to_rational(&self) -> Option<Parsed> {
+        match self {
+            Parsed::Bool(b) => {
+                Some(Parsed::Num(Numeric::Integer(*b as i128).as_rational()))
+            },
+            Parsed::Num(n) => {
+                Some(Parsed::Num(n.as_rational()))
+            },
+            _ => None
+        }
+    }
+
     fn to_quotation(&self) -> Option<Parsed> {
         match self {
-            Parsed::Quotation(_) => Some(self.clone()),
+            Parsed::Quotation(..) => Some(self.clone()),
             Parsed::Error(_) => None,
             _ => {
-                let mut deque = VecDeque::new();
-                deque.push_back(self.clone());
-                Some(Parsed::Quotation(deque))
+                Some(Parsed::quotation(vec![self.clone()]))
             }
 
              //   Some(Parsed::Quotation(vec![self.clone()])),
@@ -242,7 +318,7 @@ impl Parsed {
 
     fn to_symbol(&self) -> Option<Parsed> {
         match self {
-            Parsed::Symbol(_) => Some(self.clone()),
+            Parsed::Symbol(_, _) => Some(self.clone()),
             _ => None,
         }
     }
@@ -250,7 +326,7 @@ impl Parsed {
 
     fn to_function(&self) -> Option<Parsed> {
         match self {
-            Parsed::Function(_) => Some(self.clone()),
+            Parsed::Function(_, _) => Some(self.clone()),
             _ => None,
         }
     }
@@ -259,7 +335,7 @@ impl Parsed {
         match self {
             Parsed::Error(_) => None,
             _ => {
-                Some(Parsed::String(format!("{}", self)))
+                Some(Parsed::string(format!("{}", self)))
             }
         }
     }
@@ -269,7 +345,7 @@ impl Parsed {
             Parsed::Error(_) => None,
             Parsed::List(_) => Some(self.clone()),
             _ => {
-                Some(Parsed::List(vec![self.clone()]))
+                Some(Parsed::list(vec![self.clone()]))
             }
         }
     }
@@ -278,7 +354,7 @@ impl Parsed {
 
 
 /// Uses the bitwise and operator as a shorthand for logical AND.
-impl<'a, 'b> BitAnd<&'b Parsed> for &'a Parsed {
+impl<'b> BitAnd<&'b Parsed> for &Parsed {
     type Output = Parsed;
 
     fn bitand(self, rhs: &'b Parsed) -> Self::Output {
@@ -287,7 +363,7 @@ impl<'a, 'b> BitAnd<&'b Parsed> for &'a Parsed {
 }
 
 /// Uses the bitwise or operator as shorthand for logical OR.
-impl<'a, 'b> BitOr<&'b Parsed> for &'a Parsed {
+impl<'b> BitOr<&'b Parsed> for &Parsed {
     type Output = Parsed;
 
     fn bitor(self, rhs: &'b Parsed) -> Self::Output {
@@ -311,10 +387,13 @@ impl PartialEq for Parsed {
             (Parsed::List(l1), Parsed::List(l2)) => l1.eq(l2),
             (Parsed::Bool(b1), Parsed::Bool(b2)) => b1 == b2,
             (Parsed::Error(err1), Parsed::Error(err2)) => err1 == err2,
-            (Parsed::Quotation(q), Parsed::Quotation(q2)) => {
+            (Parsed::Caught(err1), Parsed::Caught(err2)) => err1 == err2,
+            (Parsed::Maybe(m1), Parsed::Maybe(m2)) => m1 == m2,
+            (Parsed::Result(r1), Parsed::Result(r2)) => r1 == r2,
+            (Parsed::Quotation(q, _), Parsed::Quotation(q2, _)) => {
                 q.eq(q2)
             },
-            (Parsed::Function(op), Parsed::Function(op2)) => {
+            (Parsed::Function(op, _), Parsed::Function(op2, _)) => {
                 *op == *op2
             }
             (_, _) => false
@@ -346,13 +425,14 @@ impl Display for Parsed {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Parsed::Error(err) => write!(f, "{}", err),
+            Parsed::Caught(err) => write!(f, "{}", err.message()),
             Parsed::String(s) => write!(f, "\"{}\"", s),
             Parsed::Bool(b) => if *b {
                 write!(f, "True")
             } else {
                 write!(f, "False")
             },
-            Parsed::Symbol(s) => write!(f, "{}", s),
+            Parsed::Symbol(s, _) => write!(f, "{}", s),
             Parsed::List(list) => {
                 write!(f, "[")?;
                 let mut iter = list.iter();
@@ -364,8 +444,8 @@ impl Display for Parsed {
                 }
                 write!(f, "]")
             },
-            Parsed::Function(op) => write!(f, "{}", op),
-            Parsed::Quotation(c) => {
+            Parsed::Function(op, _) => write!(f, "{}", op),
+            Parsed::Quotation(c, _) => {
                 write!(f, "{{ ")?;
                 let mut iter = c.iter();
                 if let Some(first) = iter.next() {
@@ -377,16 +457,21 @@ impl Display for Parsed {
                 write!(f, " }}")
             },
             Parsed::Num(n) => write!(f, "{}", n),
+            Parsed::Maybe(Some(value)) => write!(f, "Just {}", value),
+            Parsed::Maybe(None) => write!(f, "Nothing"),
+            Parsed::Result(Ok(value)) => write!(f, "Ok {}", value),
+            Parsed::Result(Err(reason)) => write!(f, "Failure {}", reason),
             _ => write!(f, "something else") //TODO: Error here?
         }
     }
 }
 
-/// Wraps Display for simplicity
+/// Wraps Display for simplicity. Strings are written as escaped literals, so the
+/// output can be read back by the lexer.
 impl Debug for Parsed {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Parsed::String(s) => write!(f, "\" {} \"", s),
+            Parsed::String(s) => write!(f, "\" {} \"", s.replace('\\', "\\\\").replace('"', "\\\"")),
             Parsed::List(list) => {
                 write!(f, "[ ")?;
                 let mut iter = list.iter();
@@ -398,7 +483,7 @@ impl Debug for Parsed {
                 }
                 write!(f, " ]")
             },
-            Parsed::Quotation(c) => {
+            Parsed::Quotation(c, _) => {
                 write!(f, "{{ ")?;
                 let mut iter = c.iter();
                 if let Some(first) = iter.next() {
@@ -409,6 +494,10 @@ impl Debug for Parsed {
                 }
                 write!(f, " }}")
             },
+            Parsed::Maybe(Some(value)) => write!(f, "{:?} just", value),
+            Parsed::Maybe(None) => write!(f, "nothing"),
+            Parsed::Result(Ok(value)) => write!(f, "{:?} ok", value),
+            Parsed::Result(Err(reason)) => write!(f, "{:?} failure", reason),
 
             other => write!(f, "{}", other) //TODO: Error here?
         }
@@ -2,109 +2,175 @@ use std::collections::{VecDeque};
 use crate::numeric::Numeric;
 use crate::parsed::Parsed;
 use crate::op::Op;
-use crate::utility::to_tokens;
+use crate::lexer::{to_tokens, tokenize, Token, TokenKind};
+use crate::name::Name;
+use crate::parse_error::{closing, ParseError};
+use crate::span::Source;
+use crate::stack_error::StackError;
 
 
 /// Parses string tokens into the Parsed enum type, capable of representing
 /// a predefined set of types and functions, such as +, -, float and integer.
+/// Symbols and functions keep the span of the token they were parsed from.
+///
+/// Parsing recovers from unbalanced and mismatched brackets by closing them where
+/// the problem was found, so that all problems in the input are reported at once.
 ///
 /// # Arguments
 ///
 /// * `tokens` - A vector of string tokens to be parsed.
 ///
+/// # Errors
+///
+/// Returns a ParseError for invalid tokens from the lexer, unclosed or unmatched
+/// brackets, and brackets closed by the wrong kind of bracket. If several problems
+/// are found, they are returned together as ParseError::Multiple.
+///
 /// # Examples
 ///
 /// ```
-/// use std::collections::VecDeque;
+/// use bprog::lexer::to_tokens;
+/// use bprog::parse_error::ParseError;
 /// use bprog::parsing::{parse, parse_to_quotation};
-/// use bprog::utility::to_tokens;
 ///
-/// let mut  tokens = VecDeque::from(to_tokens("{ 1 + }"));
+/// let mut  tokens = to_tokens("{ 1 + }");
 /// let expected = parse_to_quotation("1 +".to_string());
 ///
-/// assert_eq!(expected, parse(&mut tokens).pop().unwrap())
+/// assert_eq!(expected, parse(&mut tokens).unwrap().pop().unwrap());
 ///
+/// let error = parse(&mut to_tokens("{ 1 ] [")).unwrap_err();
+/// assert!(matches!(error, ParseError::Multiple(_)));
+/// assert_eq!(2, error.errors().len());
 /// ```
 ///
 ///
-pub fn parse(tokens: &mut VecDeque<String>) -> Vec<Parsed> {
+pub fn parse(tokens: &mut VecDeque<Token>) -> Result<Vec<Parsed>, ParseError> {
     let mut parsed: Vec<Parsed> = vec![];
+    // Brackets that are still open, along with what was parsed before them.
+    let mut open: Vec<(Token, Vec<Parsed>)> = vec![];
+    let mut errors: Vec<ParseError> = vec![];
 
     while let Some(t) = tokens.pop_front() {
-        if let Some(p) = parse_primitives(t.as_str()) {
+        match t.kind {
+            TokenKind::Invalid(error) => {
+                errors.push(error);
+                continue;
+            },
+            TokenKind::String => {
+                parsed.push(Parsed::string(t.text));
+                continue;
+            },
+            TokenKind::Comment => continue,
+            TokenKind::Word => {},
+        }
+        if let Some(p) = parse_primitives(t.text.as_str()) {
             parsed.push(p);
             continue;
         };
-        if let Some(p) = parse_operations(t.as_str()) {
+        if let Some(p) = parse_operations(&t) {
             parsed.push(p);
             continue;
         };
-        match t.as_str() {
+        match t.text.as_str() {
             "}" | "]" =>  {
-                return parsed;
+                let matching = open.iter().rposition(|(o, _)| closing(&o.text) == t.text);
+                match (matching, open.pop()) {
+                    (_, None) => {
+                        errors.push(ParseError::UnmatchedBracket(t.text, t.span));
+                    },
+                    (None, Some((o, outer))) => {
+                        errors.push(ParseError::MismatchedBracket {
+                            open: o.text.clone(),
+                            open_span: o.span.clone(),
+                            close: t.text,
+                            span: t.span,
+                        });
+                        parsed = close_bracket(&o, parsed, outer);
+                    },
+                    (Some(i), Some((mut o, mut outer))) => {
+                        // Brackets opened after the matching one were never closed.
+                        while open.len() > i {
+                            errors.push(ParseError::UnclosedBracket(o.text.clone(), o.span.clone()));
+                            parsed = close_bracket(&o, parsed, outer);
+                            (o, outer) = open.pop().expect("matching bracket is still open");
+                        }
+                        parsed = close_bracket(&o, parsed, outer);
+                    }
+                }
             },
             "{" | "[" => {
-                let content: Vec<Parsed>;
-                content = parse(tokens);
-                parsed.push(if t == "{" {
-                    Parsed::Quotation(VecDeque::from(content.clone())) }
-                else {
-                    Parsed::List(content.clone())
-                });
-            },
-            "\"" => {
-                let result = get_section(tokens, "\"");
-                match result {
-                    Some(section) => {
-                        parsed.push(Parsed::String(section.join(" ")));
-                    }
-                    None => {panic!("\x1b[31merr: failed to find terminating \" token for string \
-                    while parsing input.\x1b[0m")}
-                };
+                open.push((t, std::mem::take(&mut parsed)));
             },
             other => {
-                parsed.push(Parsed::Symbol(other.to_string()));
+                parsed.push(Parsed::Symbol(Name::new(other), t.span.clone()));
             }
         };
-    }{};
-    return parsed;
+    }
+    while let Some((o, outer)) = open.pop() {
+        errors.push(ParseError::UnclosedBracket(o.text.clone(), o.span.clone()));
+        parsed = close_bracket(&o, parsed, outer);
+    }
+    errors.sort_by_key(|e| e.span().map(|span| (span.line, span.col)));
+    match ParseError::from_errors(errors) {
+        Some(error) => Err(error),
+        None => Ok(parsed),
+    }
 }
 
-/// Extracts a section of a VecDeque<String> container, stopping when finding
-/// the delimiting string. Not finding the delimiter in the container body is
-/// considered a failure.
-///
-/// # Arguments
-///
-/// `tokens` - container section is removed from.
-///
-/// `delimiter` - Stop condition. The matching string is removed from `tokens`.
+/// Checks whether input only fails to parse because brackets, strings or block
+/// comments are left open, meaning that more input could complete it.
 ///
 /// # Examples
 ///
 /// ```
-/// use std::collections::VecDeque;
-/// use bprog::parsing::get_section;
-/// use bprog::utility::string_vec_deque;
-///
-/// let mut  tokens = string_vec_deque(&["this", "\"", "remainder"]);
-/// let expected = vec!["this".to_string()];
-/// assert_eq!(expected, get_section(&mut tokens, "\"").unwrap());
-///
-/// let mut  tokens = string_vec_deque(&["this", "remainder"]);
-/// assert_eq!(None, get_section(&mut tokens, "\""))
+/// use bprog::parsing::is_incomplete;
 ///
+/// assert!(is_incomplete("double { 2 *"));
+/// assert!(is_incomplete("\" unfinished"));
+/// assert!(!is_incomplete("double { 2 * } fun"));
+/// assert!(!is_incomplete("{ 2 * ] {"));
 /// ```
-pub fn get_section (tokens: &mut VecDeque<String>, delimiter: &str) -> Option<Vec<String>> {
-    let mut section = Vec::new();
-    while let Some(t) = tokens.pop_front() {
-        if t.eq(delimiter) {
-            return Some(section)
-        } else {
-            section.push(t.clone())
-        }
-    }{}
-    None
+pub fn is_incomplete(input: &str) -> bool {
+    match parse(&mut to_tokens(input)) {
+        Ok(_) => false,
+        Err(error) => error.errors().iter().all(|e| matches!(e,
+            ParseError::UnclosedBracket(_, _) |
+            ParseError::UnterminatedString(_) |
+            ParseError::UnterminatedComment(_)))
+    }
+}
+
+/// Wraps the contents of a bracket in a quotation or list, and appends it to what
+/// was parsed before the bracket was opened.
+fn close_bracket(open: &Token, contents: Vec<Parsed>, mut outer: Vec<Parsed>) -> Vec<Parsed> {
+    outer.push(if open.text == "{" {
+        Parsed::quotation(with_locals(contents))
+    } else {
+        Parsed::list(contents)
+    });
+    outer
+}
+
+/// Replaces a header of names followed by `->` at the start of a quotation by the op
+/// that binds them, so that `{ a b -> a b + }` adds the two values on top of the stack.
+fn with_locals(mut contents: Vec<Parsed>) -> Vec<Parsed> {
+    let names_end = contents.iter()
+        .position(|p| !matches!(p, Parsed::Symbol(name, _) if name.as_str() != "->"));
+    let Some(end) = names_end else {
+        return contents;
+    };
+    let span = match &contents[end] {
+        Parsed::Symbol(_, span) if end > 0 => span.clone(),
+        _ => return contents,
+    };
+    let names = contents.drain(..end)
+        .filter_map(|p| match p {
+            Parsed::Symbol(name, _) => Some(name),
+            _ => None,
+        })
+        .collect();
+    contents[0] = Parsed::Function(Op::Locals(names), span);
+    contents
 }
 
 
@@ -140,10 +206,10 @@ pub fn parse_primitives(token: & str) -> Option<Parsed> {
     if let Ok(val) = token.parse::<f64>() {
         return Some(Parsed::Num(Numeric::Float(val)));
     }
-    return None;
+    None
 }
 
-/// Parses Parsed::Function from &str. Relies on Parsed implementation of FromStr.
+/// Parses Parsed::Function from a token. Relies on Op implementation of FromStr.
 ///
 /// # Examples
 ///
@@ -151,16 +217,18 @@ pub fn parse_primitives(token: & str) -> Option<Parsed> {
 /// use bprog::op::Op;
 /// use bprog::parsed::Parsed;
 /// use bprog::parsing::parse_operations;
+/// use bprog::lexer::Token;
+/// use bprog::span::Span;
 ///
-/// let expected = Parsed::Function(Op::Add);
-/// let test = parse_operations("+").unwrap();
+/// let expected = Parsed::Function(Op::Add, Span::default());
+/// let test = parse_operations(&Token::new("+", Span::default())).unwrap();
 ///
 /// assert_eq!(expected, test);
 ///
 /// ```
-pub fn parse_operations(token: & str) -> Option<Parsed> {
-    if let Ok(op) = token.parse::<Op>() {
-        return Some(Parsed::Function(op))
+pub fn parse_operations(token: &Token) -> Option<Parsed> {
+    if let Ok(op) = token.text.parse::<Op>() {
+        return Some(Parsed::Function(op, token.span.clone()))
     }
     None
 }
@@ -174,38 +242,14 @@ pub fn parse_operations(token: & str) -> Option<Parsed> {
 /// use bprog::parsed::Parsed;
 /// use bprog::parsing::parse_to_quotation;
 ///
-/// let expected = Parsed::Quotation(VecDeque::from(vec![Parsed::Bool(true)]));
+/// let expected = Parsed::quotation(VecDeque::from(vec![Parsed::Bool(true)]));
 /// let test = parse_to_quotation("True".to_string());
 ///
 /// assert_eq!(expected, test)
 /// ```
 pub fn parse_to_quotation(string: String) -> Parsed {
-    let parsed = parse(&mut VecDeque::from(to_tokens(&mut string.to_string())));
-    Parsed::Quotation(VecDeque::from(parsed))
+    match parse(&mut tokenize(&Source::new("<generated>", &string))) {
+        Ok(parsed) => Parsed::quotation(parsed),
+        Err(error) => Parsed::Error(StackError::Parse(error)),
+    }
 }
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
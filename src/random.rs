@@ -0,0 +1,123 @@
+/////////////////////////// RANDOM ////////////////////////////////////////////////////////////////
+
+use num::{BigInt, ToPrimitive, Zero};
+
+/// Seed used by programs that never set one.
+pub const DEFAULT_SEED: u64 = 0;
+
+#[derive(Clone, Debug, PartialEq)]
+/// Seedable pseudo-random number generator behind the random operations. It uses the
+/// xoshiro256** algorithm with its state filled in by SplitMix64, so a seed gives the
+/// same sequence of numbers on every run and platform.
+///
+/// # Examples
+///
+/// ```
+/// use bprog::random::Rng;
+///
+/// let mut first = Rng::new(42);
+/// let mut second = Rng::new(42);
+///
+/// assert_eq!(first.next_u64(), second.next_u64());
+/// assert!(first.below(6) < 6);
+/// ```
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(DEFAULT_SEED)
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        let mut seed = seed;
+        let mut state = [0; 4];
+        for word in state.iter_mut() {
+            seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            *word = z ^ (z >> 31);
+        }
+        Rng { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.state[1] << 17;
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= t;
+        self.state[3] = self.state[3].rotate_left(45);
+        result
+    }
+
+    /// Returns a float in the range [0, 1), with 53 random bits.
+    pub fn next_float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a number in the range [0, bound). The bound must not be 0.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        // Values above the largest multiple of the bound are rejected, so that
+        // every result is equally likely.
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let val = self.next_u64();
+            if val < zone {
+                return val % bound;
+            }
+        }
+    }
+
+    /// Returns a number in the range [0, bound), for bounds of any size. The bound
+    /// must be positive.
+    pub fn below_big(&mut self, bound: &BigInt) -> BigInt {
+        let bits = bound.bits();
+        let words = bits.div_ceil(64);
+        let excess = words * 64 - bits;
+        loop {
+            let mut val = BigInt::zero();
+            for _ in 0..words {
+                val = (val << 64) + self.next_u64();
+            }
+            val >>= excess;
+            if &val < bound {
+                return val;
+            }
+        }
+    }
+
+    /// Returns an integer in the range [low, high]. The bounds must be in order.
+    pub fn between(&mut self, low: &BigInt, high: &BigInt) -> BigInt {
+        let size: BigInt = high - low + 1;
+        match size.to_u64() {
+            Some(size) => low + self.below(size),
+            None => low + self.below_big(&size),
+        }
+    }
+
+    /// Puts the items in a random order, with every order equally likely.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    /// Picks `n` items at random positions, in random order. Each position is
+    /// picked at most once, and `n` must not exceed the number of items.
+    pub fn sample<T: Clone>(&mut self, items: &[T], n: usize) -> Vec<T> {
+        let mut positions: Vec<usize> = (0..items.len()).collect();
+        for i in 0..n {
+            let j = i + self.below((positions.len() - i) as u64) as usize;
+            positions.swap(i, j);
+        }
+        positions[..n].iter().map(|&i| items[i].clone()).collect()
+    }
+}
@@ -0,0 +1,178 @@
+/////////////////////////// SCOPE /////////////////////////////////////////////////////////////////
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::compiler::Code;
+use crate::interpreter::Binding;
+use crate::name::Name;
+use crate::parsed::Parsed;
+
+/// The names bound by one run of a quotation. Names not bound in a scope are looked up
+/// in the scope it is nested in, the one the quotation was written in, and last among
+/// the global bindings of the Env. Quotations pushed as values keep the scope they were
+/// pushed in, so the names they refer to outlive the run that bound them.
+///
+/// # Examples
+///
+/// ```
+/// use bprog::interpreter::Binding;
+/// use bprog::name::Name;
+/// use bprog::parsed::Parsed;
+/// use bprog::scope::Scope;
+///
+/// let outer = Scope::new(None);
+/// outer.bind(Name::new("x"), Binding { function: false, constant: false, value: Parsed::Bool(true) });
+/// let inner = Scope::new(Some(outer));
+///
+/// assert_eq!(Some(Parsed::Bool(true)), inner.get(&Name::new("x")).map(|binding| binding.value));
+/// assert!(inner.get(&Name::new("y")).is_none());
+/// ```
+pub struct Scope {
+    locals: RefCell<Vec<Local>>,
+    parent: Option<Rc<Scope>>,
+    kind: Kind,
+}
+
+/// The code a scope is made for a run of.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Quotation,
+    /// The body of a function called by name.
+    Call,
+    /// A module, standing in for the top level of it.
+    Module,
+}
+
+/// A name bound in a scope. Scopes hold few names, so they are searched in order.
+struct Local {
+    name: Name,
+    binding: Binding,
+    /// Code compiled from the bound value by the VM, cleared when the name is rebound.
+    compiled: Option<Code>,
+    /// Whether the value is a quotation pushed in the scope holding it. It is kept
+    /// without the scope, which would otherwise own itself and never be dropped, and
+    /// given the scope back when it is looked up.
+    captures_scope: bool,
+}
+
+impl Local {
+    fn new(name: Name, binding: Binding, owner: &Scope) -> Local {
+        let (binding, captures_scope) = detach(binding, owner);
+        Local { name, binding, compiled: None, captures_scope }
+    }
+
+    /// Replaces the binding of a local of the scope `owner`.
+    fn set(&mut self, binding: Binding, owner: &Scope) {
+        (self.binding, self.captures_scope) = detach(binding, owner);
+        self.compiled = None;
+    }
+
+    /// Returns the binding of a local of the scope `owner`.
+    fn binding(&self, owner: &Rc<Scope>) -> Binding {
+        match &self.binding.value {
+            Parsed::Quotation(code, _) if self.captures_scope => Binding {
+                value: Parsed::Quotation(Rc::clone(code), Some(Rc::clone(owner))),
+                ..self.binding.clone()
+            },
+            _ => self.binding.clone(),
+        }
+    }
+}
+
+impl Scope {
+    /// Constructs a new empty scope, nested in `parent` or directly in the global scope.
+    pub fn new(parent: Option<Rc<Scope>>) -> Rc<Scope> {
+        Rc::new(Scope { locals: RefCell::new(Vec::new()), parent, kind: Kind::Quotation })
+    }
+
+    /// Constructs the scope of a call of a function, nested in the scope the function
+    /// was written in. It hides the top level from assignments in the function.
+    pub fn call(parent: Option<Rc<Scope>>) -> Rc<Scope> {
+        Rc::new(Scope { locals: RefCell::new(Vec::new()), parent, kind: Kind::Call })
+    }
+
+    /// Constructs the scope a module runs in. Names bound globally are seen by the
+    /// module, but assigning them binds them in this scope instead of rebinding them.
+    pub fn module() -> Rc<Scope> {
+        Rc::new(Scope { locals: RefCell::new(Vec::new()), parent: None, kind: Kind::Module })
+    }
+
+    /// Returns whether the scope is nested only in quotations written at the top level
+    /// of the program, and not in a function called or a module.
+    pub fn at_top_level(&self) -> bool {
+        let mut scope = self;
+        loop {
+            if scope.kind != Kind::Quotation {
+                return false;
+            }
+            match &scope.parent {
+                Some(parent) => scope = parent,
+                None => return true,
+            }
+        }
+    }
+
+    /// Returns the binding of a name in this scope or the closest scope around it.
+    pub fn get(self: &Rc<Self>, name: &Name) -> Option<Binding> {
+        self.find(name, |owner, local| local.binding(owner))
+    }
+
+    /// Binds a name in this scope, replacing any binding it has here already.
+    pub fn bind(&self, name: Name, binding: Binding) {
+        let mut locals = self.locals.borrow_mut();
+        match locals.iter_mut().find(|local| local.name == name) {
+            Some(local) => local.set(binding, self),
+            None => locals.push(Local::new(name, binding, self)),
+        }
+    }
+
+    /// Returns whether a name is bound in this scope or the scopes around it.
+    pub fn binds(self: &Rc<Self>, name: &Name) -> bool {
+        self.find(name, |_, _| ()).is_some()
+    }
+
+    /// Rebinds a name in the closest scope binding it, returning false if none does.
+    pub fn rebind(self: &Rc<Self>, name: &Name, binding: Binding) -> bool {
+        self.find(name, |owner, local| local.set(binding, owner)).is_some()
+    }
+
+    /// Returns the names bound in this scope itself, in the order they were first bound.
+    pub fn bindings(self: &Rc<Self>) -> Vec<(Name, Binding)> {
+        self.locals.borrow().iter()
+            .map(|local| (local.name.clone(), local.binding(self)))
+            .collect()
+    }
+
+    /// Returns the code compiled for the binding `get` would return, if there is any.
+    pub fn compiled(self: &Rc<Self>, name: &Name) -> Option<Code> {
+        self.find(name, |_, local| local.compiled.clone()).flatten()
+    }
+
+    /// Keeps the code compiled for the binding `get` would return.
+    pub fn set_compiled(self: &Rc<Self>, name: &Name, code: Code) {
+        self.find(name, |_, local| local.compiled = Some(code));
+    }
+
+    fn find<R>(self: &Rc<Self>, name: &Name, f: impl FnOnce(&Rc<Scope>, &mut Local) -> R) -> Option<R> {
+        let mut scope = self;
+        loop {
+            let mut locals = scope.locals.borrow_mut();
+            if let Some(local) = locals.iter_mut().find(|local| &local.name == name) {
+                return Some(f(scope, local));
+            }
+            drop(locals);
+            scope = scope.parent.as_ref()?;
+        }
+    }
+}
+
+/// Takes the scope out of a quotation pushed in the scope `owner`, returning whether
+/// it did.
+fn detach(binding: Binding, owner: &Scope) -> (Binding, bool) {
+    match binding.value {
+        Parsed::Quotation(code, Some(scope)) if std::ptr::eq(&*scope, owner) => {
+            (Binding { value: Parsed::Quotation(code, None), ..binding }, true)
+        },
+        value => (Binding { value, ..binding }, false),
+    }
+}
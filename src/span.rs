@@ -0,0 +1,111 @@
+/////////////////////////// SPAN //////////////////////////////////////////////////////////////////
+
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+/// A named body of program text, shared by every span pointing into it so that
+/// error messages can quote the offending line.
+#[derive(Debug, PartialEq)]
+pub struct Source {
+    pub name: String,
+    pub text: String,
+}
+
+impl Source {
+    /// Wraps program text and the name it should be reported under.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bprog::span::Source;
+    ///
+    /// let source = Source::new("main.bprog", "1 2 +\n3 *");
+    /// assert_eq!(Some("3 *"), source.line(2));
+    /// ```
+    pub fn new(name: &str, text: &str) -> Rc<Source> {
+        Rc::new(Source { name: name.to_string(), text: text.to_string() })
+    }
+
+    /// Returns the line with the given 1-based number, if it exists.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        if line == 0 {
+            return None;
+        }
+        self.text.lines().nth(line - 1)
+    }
+}
+
+#[derive(Clone, Default)]
+/// The location of a token in its source, given as a 1-based line and column.
+/// Spans created at runtime, which have no source, are displayed as `<unknown>`.
+pub struct Span {
+    pub source: Option<Rc<Source>>,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    pub fn new(source: &Rc<Source>, line: usize, col: usize) -> Span {
+        Span { source: Some(source.clone()), line, col }
+    }
+
+    /// Name of the source the span points into.
+    pub fn file(&self) -> &str {
+        match &self.source {
+            Some(source) => source.name.as_str(),
+            None => "<unknown>",
+        }
+    }
+
+    /// Formats the location, the line it points into, and a caret under the
+    /// column, followed by the given message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bprog::span::{Source, Span};
+    ///
+    /// let source = Source::new("main.bprog", "1 2 +\n3 foo *");
+    /// let span = Span::new(&source, 2, 3);
+    /// assert_eq!("main.bprog:2:3\n 2 | 3 foo *\n   |   ^\nbad", span.report("bad"));
+    /// ```
+    pub fn report(&self, message: &str) -> String {
+        let line = match &self.source {
+            Some(source) => source.line(self.line),
+            None => None,
+        };
+        match line {
+            Some(text) => {
+                let number = self.line.to_string();
+                let gutter = " ".repeat(number.len());
+                let offset: String = text.chars()
+                    .take(self.col.saturating_sub(1))
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                format!("{}\n {} | {}\n {} | {}^\n{}", self, number, text, gutter, offset, message)
+            },
+            None => format!("{}\n{}", self, message),
+        }
+    }
+}
+
+/// Spans are equal when they point to the same position in equally named sources.
+impl PartialEq for Span {
+    fn eq(&self, other: &Self) -> bool {
+        self.file() == other.file() && self.line == other.line && self.col == other.col
+    }
+}
+
+/// Displays the span as "file:line:col".
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file(), self.line, self.col)
+    }
+}
+
+impl Debug for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
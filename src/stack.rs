@@ -1,31 +1,42 @@
 //! This module implements a traditional stack data structure with the
 //! usual top, pop, and push methods along with iterator methods.
 //!
-//! The Stack is represented as an enum with two variants:
-//! - Empty: represents an empty stack
-//! - Top: represents a non-empty stack and holds the current value on top of
-//!        the stack, a pointer to the bottom of the stack, and the current
-//!        size of the stack.
+//! The Stack keeps its elements in a contiguous vector, with the bottom of
+//! the stack first and the top last, so pushing and popping rarely allocate
+//! and any element can be reached in constant time.
 //!
 //! The Stack object provides methods to push, pop, and retrieve the top
 //! element without popping it off the stack. The object also provides
 //! methods to display all contents of the stack, check if the stack is
 //! empty, retrieve the element count of the stack, and create iterators
-//! over the stack's contents.
+//! over the stack's contents, from the top or from the bottom. Elements
+//! further down can be looked at with peek, and several elements can be
+//! taken off at once with split_off and truncate.
 //!
 //! Additionally, this module provides the implementation of StackIter
 //! and the ability to create a new stack from an iterator using `.collect()`.
 //!
 //! # Examples
 //!
+//! ```
+//! use bprog::stack::Stack;
+//!
+//! let mut stack: Stack<i32> = (1..=4).collect();
+//!
+//! assert_eq!(Some(&4), stack.top());
+//! assert_eq!(Some(&2), stack.peek(2));
+//! assert_eq!(Some(vec![3, 4]), stack.split_off(2));
+//! assert_eq!("2 1", stack.contents_to_string());
+//! ```
 use std::fmt::{Debug, Display};
+use std::iter::Rev;
+use std::slice;
 
 #[derive(PartialEq, Clone, Debug)]
 /// Stack implements a traditional stack data structure with the
 /// usual top, pop, and push methods along with iterator methods.
-pub enum Stack<T: Clone + Display + Debug> {
-    Empty,
-    Top(T, Box<Stack<T>>, usize)
+pub struct Stack<T: Clone + Display + Debug> {
+    items: Vec<T>,
 }
 
 
@@ -42,7 +53,7 @@ impl<T:Clone + Display + Debug> Stack<T> {
     ///
     /// let mut stack = Stack::new();
     /// stack.push(Parsed::Bool(true));
-    /// stack.push(Parsed::String("hello world!".to_string()));
+    /// stack.push(Parsed::string("hello world!"));
     /// assert_eq!("\"hello world!\" True", stack.contents_to_string())
     /// ```
     pub fn contents_to_string(&self) -> String {
@@ -52,12 +63,12 @@ impl<T:Clone + Display + Debug> Stack<T> {
             output.push(' ');
         });
         output.pop();
-        format!("{}", output)
+        output
     }
 
     /// Constructs a new empty stack
     pub fn new() -> Self {
-        Stack::Empty
+        Stack { items: Vec::new() }
     }
 
     /// Pushes a new value T onto the stack.
@@ -67,14 +78,7 @@ impl<T:Clone + Display + Debug> Stack<T> {
     ///
     ///
     pub fn push(&mut self, val: T) {
-        match self {
-            Stack::Empty=> *self = Stack::Top(val, Box::new(Stack::Empty), 1),
-            Stack::Top(_, _, size) => {
-                let new_size = *size + 1;
-                let old_stack = Box::new(std::mem::replace(self, Stack::Empty));
-                *self = Stack::Top(val, old_stack, new_size)
-            }
-        }
+        self.items.push(val)
     }
 
     /// Pops the top value off of the stack and returns the value.
@@ -86,13 +90,7 @@ impl<T:Clone + Display + Debug> Stack<T> {
     ///
     ///
     pub fn pop(&mut self) -> Option<T> {
-        match std::mem::replace(self, Stack::Empty) {
-            Stack::Empty => None,
-            Stack::Top(val, bottom, _) => {
-                *self = *bottom;
-                Some(val)
-            }
-        }
+        self.items.pop()
     }
 
     /// Returns a reference to the top element without popping it off the stack.
@@ -102,10 +100,36 @@ impl<T:Clone + Display + Debug> Stack<T> {
     /// None otherwise.
     ///
     pub fn top(&self) -> Option<&T> {
-        match self {
-            Stack::Top(val, _ ,_) => Some(val),
-            _ => None
-        }
+        self.items.last()
+    }
+
+    /// Returns a reference to the element `n` places below the top, so that
+    /// `peek(0)` is the top element.
+    ///
+    /// # Returns
+    /// Some(T) if the stack holds more than `n` elements,
+    /// None otherwise.
+    ///
+    pub fn peek(&self, n: usize) -> Option<&T> {
+        self.items.len().checked_sub(n + 1).map(|i| &self.items[i])
+    }
+
+    /// Takes the top `n` elements off the stack.
+    ///
+    /// # Returns
+    ///
+    /// Some(Vec<T>) with the elements in the order they were pushed, the top
+    /// element last, if the stack holds at least `n` elements. Otherwise None,
+    /// and the stack is left as it was.
+    ///
+    pub fn split_off(&mut self, n: usize) -> Option<Vec<T>> {
+        let at = self.items.len().checked_sub(n)?;
+        Some(self.items.split_off(at))
+    }
+
+    /// Drops elements from the top of the stack until at most `len` are left.
+    pub fn truncate(&mut self, len: usize) {
+        self.items.truncate(len)
     }
 
     /// Checks if the stack is empty
@@ -115,10 +139,7 @@ impl<T:Clone + Display + Debug> Stack<T> {
     /// true if empty, false otherwise;
     ///
     pub fn is_empty(&self) -> bool {
-        match self {
-            Stack::Empty => true,
-            _ => false
-        }
+        self.items.is_empty()
     }
 
     /// Retrieves the element count of the stack
@@ -128,28 +149,41 @@ impl<T:Clone + Display + Debug> Stack<T> {
     /// The amount of elements currently on the stack
     ///
     pub fn size(&self) -> usize {
-        match self {
-            Stack::Empty => 0,
-            Stack::Top(_, _, size) => *size
-        }
+        self.items.len()
     }
 
     /// Empties the stack of all contents.
     pub fn clear(&mut self) {
-        while !self.is_empty() { self.pop().unwrap(); }
+        self.items.clear()
     }
 
-    /// Returns an iterator over the stacks contents.
-    pub fn iter(&self) -> impl Iterator<Item=&T> {
+    /// Returns an iterator over the stacks contents, from the top down.
+    pub fn iter(&self) -> StackIter<'_, T> {
         self.into_iter()
     }
+
+    /// Returns an iterator over the stacks contents, from the bottom up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bprog::stack::Stack;
+    ///
+    /// let stack: Stack<i32> = vec![1, 2, 3].into_iter().collect();
+    ///
+    /// assert_eq!(vec![&1, &2, &3], stack.iter_from_bottom().collect::<Vec<_>>());
+    /// assert_eq!(vec![&3, &2, &1], stack.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn iter_from_bottom(&self) -> slice::Iter<'_, T> {
+        self.items.iter()
+    }
 }
 
 
 
 /// Implements StackIter
 pub struct StackIter<'a, T:Clone + Display + Debug> {
-    stack: &'a Stack<T>,
+    items: Rev<slice::Iter<'a, T>>,
 }
 
 /// Implements IntoIterator for Stack, allowing the creation of an
@@ -158,7 +192,7 @@ impl<'a, T:Clone + Display + Debug> IntoIterator for &'a Stack<T> {
     type Item = &'a T;
     type IntoIter = StackIter<'a, T>;
     fn into_iter(self) -> Self::IntoIter {
-        StackIter { stack: self }
+        StackIter { items: self.items.iter().rev() }
     }
 }
 
@@ -166,19 +200,15 @@ impl<'a, T:Clone + Display + Debug> IntoIterator for &'a Stack<T> {
 ///  a new stack from an iterator.
 impl<T:Clone + Display + Debug> FromIterator<T> for Stack<T> {
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
-        let mut stack = Stack::Empty;
-        for val in iter {
-            stack.push(val);
-        }
-        stack
+        Stack { items: iter.into_iter().collect() }
     }
 }
 
 /// Implements Default value for a stack. Using Stack::new() will use the
-/// default to initialize the Stack object as Stack::Empty.
+/// default to initialize the Stack object as an empty stack.
 impl<T:Clone + Display + Debug> Default for Stack<T> {
     fn default() -> Self {
-        Stack::Empty
+        Stack::new()
     }
 }
 
@@ -203,15 +233,18 @@ impl <'a, T:Clone + Display + Debug> Iterator for StackIter<'a, T> {
     /// assert_eq!(None, iter.next());
     /// ```
     fn next(&mut self) -> Option<Self::Item> {
-        match self.stack {
-            Stack::Top(val, bottom,_) => {
-                self.stack = bottom;
-                Some(val)
-            }
-            Stack::Empty => {
-                None
-            }
-        }
+        self.items.next()
     }
 
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
 }
+
+impl <T:Clone + Display + Debug> DoubleEndedIterator for StackIter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.items.next_back()
+    }
+}
+
+impl <T:Clone + Display + Debug> ExactSizeIterator for StackIter<'_, T> {}
@@ -2,8 +2,11 @@ use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::ops::Add;
 use crate::op::Op;
+use crate::parse_error::ParseError;
+use crate::parsed::Parsed;
 use crate::types::Params;
 use crate::interpreter::{Args};
+use crate::span::Span;
 
 #[derive(Clone, Debug, PartialEq)]
 /// StackError represents various computational errors that can occur during program
@@ -12,8 +15,10 @@ pub enum StackError { // TODO: Keep as a single error type, or make specific var
     // Arithmetic errors
     Overflow,
     ZeroDiv,
+    Domain(String),
     // Operational errors
     InvalidCoercion,
+    InvalidArgument(String),
     Undefined,
     // Constraint errors
     TypeMismatch(String),
@@ -25,9 +30,17 @@ pub enum StackError { // TODO: Keep as a single error type, or make specific var
     // Stack errors
     PopEmpty,
     PrematureEnd,
+    DepthLimit(usize),
+
+    // Module errors
+    Module(String),
+    /// The paths of the modules importing each other, starting and ending with the same one.
+    ImportCycle(Vec<String>),
 
     // Others
-    UserDefined(String),
+    /// An error raised by `err` or `throw`, with the value it was raised with.
+    UserDefined(Box<Parsed>),
+    Parse(ParseError),
 }
 
 /// Implements Display for StackError, writing "err: <specific error message>"
@@ -37,12 +50,66 @@ impl Display for StackError {
         match self {
             StackError::Overflow => write!(f, "\x1b[31merr: numeric overflow\x1b[0m"),
             StackError::ZeroDiv=> write!(f, "\x1b[31merr: zero division\x1b[0m"),
+            StackError::Domain(s) => write!(f, "\x1b[31merr: {}\x1b[0m", s),
             StackError::PopEmpty => write!(f, "\x1b[31merr: attempted to pop empty stack!\x1b[0m"),
+            StackError::DepthLimit(limit) => write!(f, "\x1b[31merr: more than {} nested calls, see setDepthLimit\x1b[0m", limit),
             StackError::PrematureEnd => write!(f, "\x1b[31mexpected more program input, but none was found.\x1b[0m"),
+            StackError::HeadEmpty => write!(f, "\x1b[31merr: head of an empty list\x1b[0m"),
+            StackError::TailEmpty => write!(f, "\x1b[31merr: tail of an empty list\x1b[0m"),
+            StackError::Undefined => write!(f, "\x1b[31merr: undefined operation\x1b[0m"),
             StackError::InvalidCoercion => write!(f, "\x1b[31merr: cannot coerce operands to target type\x1b[0m"),
+            StackError::InvalidArgument(s) => write!(f, "\x1b[31merr: {}\x1b[0m", s),
             StackError::TypeMismatch(s) => write!(f, "{}", s),
-            StackError::UserDefined(s) => write!(f, "\x1b[31m{}\x1b[0m", s),
-            _ => write!(f, "not implemented")
+            StackError::UserDefined(payload) => match payload.as_ref() {
+                Parsed::String(s) => write!(f, "\x1b[31m{}\x1b[0m", s),
+                payload => write!(f, "\x1b[31m{}\x1b[0m", payload),
+            },
+            StackError::Parse(err) => write!(f, "{}", err),
+            StackError::Module(s) => write!(f, "\x1b[31merr: {}\x1b[0m", s),
+            StackError::ImportCycle(chain) => write!(f, "\x1b[31merr: import cycle: {}\x1b[0m", chain.join(" -> ")),
+        }
+    }
+}
+
+impl StackError {
+    /// Returns the message of the error without the colours it is displayed with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bprog::stack_error::StackError;
+    ///
+    /// assert_eq!("err: zero division", StackError::ZeroDiv.message());
+    /// ```
+    pub fn message(&self) -> String {
+        let text = self.to_string();
+        let mut message = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|c| c.is_ascii_alphabetic());
+            } else {
+                message.push(c);
+            }
+        }
+        message
+    }
+
+    /// Returns the value the error was raised with, which is the message of errors
+    /// not raised by `err` or `throw`.
+    pub fn payload(&self) -> Parsed {
+        match self {
+            StackError::UserDefined(payload) => payload.as_ref().clone(),
+            err => Parsed::string(err.message()),
+        }
+    }
+
+    /// Formats the error for the user, prefixed with the "file:line:col" of the
+    /// token that caused it and the source line it was found on, if known.
+    pub fn report(&self, span: Option<&Span>) -> String {
+        match span {
+            Some(span) => span.report(&self.to_string()),
+            None => self.to_string(),
         }
     }
 }
@@ -54,7 +121,7 @@ pub fn arg_mismatch(op: &Op, got: &Args, stack_arg: bool) -> StackError {
     } else {
         op.get_signature().modifiers
     };
-    StackError::TypeMismatch(match (exp.clone(), got.clone()) {
+    StackError::TypeMismatch(match (exp, got) {
         (Params::Unary(expected), Args::Unary(actual)) => {
             let s = format!("err: argument of type \x1b[33m{}\x1b[0m with value \x1b[33m{}\x1b[0m does \
              not satisfy constraint in the function \x1b[36m{}\x1b[0m, with signature", actual.get_type(), actual, op);
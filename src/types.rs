@@ -92,24 +92,29 @@ pub enum Constraint {
     String,
     List,
     Integer,
+    Rational,
     Float,
     Bool,
     Quotation,
     Error,
     Symbol,
     Function(Box<Signature>),
+    Maybe,
+    Result,
 
     // TypeClasses below
     Any,
     Ord,
     Eq,
     Num,
+    Exact,
     Functor,
     Boolean,
     Enum,
     Display,
     Executable,
-    Sized
+    Sized,
+    Fallible,
 }
 
 impl Display for Constraint {
@@ -119,22 +124,27 @@ impl Display for Constraint {
             Constraint::String => write!(f, "String"),
             Constraint::List => write!(f, "List"),
             Constraint::Integer => write!(f, "Integer"),
+            Constraint::Rational => write!(f, "Rational"),
             Constraint::Float => write!(f, "Float"),
             Constraint::Bool => write!(f, "Bool"),
             Constraint::Quotation => write!(f, "Quotation"),
             Constraint::Error => write!(f, "Error"),
             Constraint::Symbol => write!(f, "Symbol"),
             Constraint::Function(_) => write!(f, "Function"),
+            Constraint::Maybe => write!(f, "Maybe"),
+            Constraint::Result => write!(f, "Result"),
             Constraint::Any => write!(f, "Any"),
             Constraint::Ord => write!(f, "Ord"),
             Constraint::Eq => write!(f, "Eq"),
             Constraint::Num => write!(f, "Num"),
+            Constraint::Exact => write!(f, "Exact"),
             Constraint::Functor => write!(f, "Functor"),
             Constraint::Boolean => write!(f, "Boolean"),
             Constraint::Enum => write!(f, "Enum"),
             Constraint::Display => write!(f, "Display"),
             Constraint::Executable => write!(f, "Executable"),
             Constraint::Sized => write!(f, "Sized"),
+            Constraint::Fallible => write!(f, "Fallible"),
         }
     }
 }
@@ -157,6 +167,9 @@ impl Constraint {
                 Constraint::Num => {
                     t.implements(&TypeClass::Num)
                 }
+                Constraint::Exact => {
+                    t.implements(&TypeClass::Exact)
+                }
                 Constraint::Functor => {
                     t.implements(&TypeClass::Functor)
                 }
@@ -174,7 +187,10 @@ impl Constraint {
                 },
                 Constraint::Sized => {
                     t.implements(&TypeClass::Sized)
-                }
+                },
+                Constraint::Fallible => {
+                    t.implements(&TypeClass::Fallible)
+                },
                 _ => false,
             }
         }
@@ -192,12 +208,15 @@ pub enum Type {
     String,
     List,
     Integer,
+    Rational,
     Float,
     Bool,
     Quotation,
     Error,
     Symbol,
-    Function(Signature)
+    Function(Signature),
+    Maybe,
+    Result,
 }
 
 
@@ -208,12 +227,15 @@ impl Type {
             Type::String => Constraint::String,
             Type::List => Constraint::List,
             Type::Integer => Constraint::Integer,
+            Type::Rational => Constraint::Rational,
             Type::Float => Constraint::Float,
             Type::Bool => Constraint::Bool,
             Type::Quotation => Constraint::Quotation,
             Type::Error => Constraint::Error,
             Type::Symbol => Constraint::Symbol,
-            Type::Function(sig) => Constraint::Function(Box::new(sig.clone()))
+            Type::Function(sig) => Constraint::Function(Box::new(sig.clone())),
+            Type::Maybe => Constraint::Maybe,
+            Type::Result => Constraint::Result,
         }
     }
 }
@@ -225,12 +247,15 @@ impl Display for Type {
             Type::String => write!(f, "String"),
             Type::List => write!(f, "List"),
             Type::Integer => write!(f, "Integer"),
+            Type::Rational => write!(f, "Rational"),
             Type::Float => write!(f, "Float"),
             Type::Bool => write!(f, "Bool"),
             Type::Quotation =>write!(f, "Quotation"),
             Type::Error => write!(f, "Error"),
             Type::Symbol => write!(f, "Symbol"),
             Type::Function(fun) => write!(f, "Func {}", fun),
+            Type::Maybe => write!(f, "Maybe"),
+            Type::Result => write!(f, "Result"),
         }
     }
 }
@@ -244,12 +269,14 @@ pub(crate) enum TypeClass {
     Ordering, // Comparisons
     Eq,
     Num, // Arithmetic operations
+    Exact, // Numbers without rounding error
     Functor, // Mapping
     Boolean, // Types with a truth value
     Enum, //
     Display,
     Executable,
-    Sized
+    Sized,
+    Fallible, // Values that may be missing or failed
 }
 
 
@@ -291,6 +318,20 @@ fn integer_implements(class: &TypeClass) -> bool {
         TypeClass::Ordering |
         TypeClass::Eq |
         TypeClass::Num |
+        TypeClass::Exact |
+        TypeClass::Boolean |
+        TypeClass::Display => true,
+        _ => false,
+    }
+}
+
+fn rational_implements(class: &TypeClass) -> bool {
+    match class {
+        TypeClass::Any |
+        TypeClass::Ordering |
+        TypeClass::Eq |
+        TypeClass::Num |
+        TypeClass::Exact |
         TypeClass::Boolean |
         TypeClass::Display => true,
         _ => false,
@@ -358,6 +399,17 @@ fn function_implements(class: &TypeClass) -> bool {
     }
 }
 
+fn fallible_implements(class: &TypeClass) -> bool {
+    match class {
+        TypeClass::Any |
+        TypeClass::Eq |
+        TypeClass::Functor |
+        TypeClass::Display |
+        TypeClass::Fallible => true,
+        _ => false
+    }
+}
+
 
 impl Type {
     fn implements(&self, class: &TypeClass) -> bool {
@@ -366,12 +418,14 @@ impl Type {
             Type::String => string_implements(class),
             Type::List => list_implements(class),
             Type::Integer => integer_implements(class),
+            Type::Rational => rational_implements(class),
             Type::Float => float_implements(class),
             Type::Bool => bool_implements(class),
             Type::Quotation => quotation_implements(class),
             Type::Error => error_implements(class),
             Type::Symbol => symbol_implements(class),
             Type::Function(_) => function_implements(class),
+            Type::Maybe | Type::Result => fallible_implements(class),
         }
     }
 }
@@ -385,9 +439,17 @@ pub fn numeric_coercion(t1: &Type, t2: &Type) -> Option<Type> {
     }
     match (t1, t2) {
         (Type::Integer, Type::Float) |
-        (Type::Float, Type::Integer) => {
+        (Type::Float, Type::Integer) |
+        (Type::Rational, Type::Float) |
+        (Type::Float, Type::Rational) => {
             Some(Type::Float)
         },
+        (Type::Integer, Type::Rational) |
+        (Type::Rational, Type::Integer) |
+        (Type::Bool, Type::Rational) |
+        (Type::Rational, Type::Bool) => {
+            Some(Type::Rational)
+        },
         (Type::Bool, Type::Integer) |
         (Type::Integer, Type::Bool) => {
             Some(Type::Integer)
@@ -45,27 +45,6 @@ pub fn get_input(in_file: Option<&mut File>) -> Result<String, io::Error> {
     }
 }
 
-/// Splits a string into tokens delimited by whitespace.
-///
-/// # Examples
-///
-/// ```
-/// use std::collections::VecDeque;
-/// use bprog::utility::{string_vec_deque, to_tokens};
-/// let tokens = string_vec_deque(&["one", "two", "three"]);
-///
-/// assert_eq!(tokens, to_tokens("one two three"));
-///
-/// ```
-///
-pub fn to_tokens(input: &str) -> VecDeque<String> {
-    input
-        .split_whitespace()
-        .map(|s| s.to_string())
-        .collect::<VecDeque<_>>()
-}
-
-
 pub fn string_vec(vec: &[&str]) -> Vec<String> {
     vec.iter().map(|s| s.to_string()).collect()
 }
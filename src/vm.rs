@@ -0,0 +1,503 @@
+/////////////////////////// VM ////////////////////////////////////////////////////////////////////
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use crate::compiler::{compile, Code, Instr, Modifier};
+use crate::interpreter::{bind_locals, body_of, open_case, transaction, Args, Binding, Body, Env, Iteration};
+use crate::module::import;
+use crate::name::Name;
+use crate::op::Op;
+use crate::parsed::Parsed;
+use crate::scope::Scope;
+use crate::stack::Stack;
+use crate::stack_error::{arg_mismatch, StackError};
+use crate::types::{Params, Signature, Type};
+
+/// Work left to do by the VM, like the frames of the interpreter but running compiled
+/// code from a position within it.
+enum Frame {
+    Code { code: Code, pc: usize, scope: Option<Rc<Scope>> },
+    Iteration(Iteration<Body<Code>>),
+}
+
+impl Frame {
+    /// Runs code in a new scope of its own if it binds names. Other code cannot tell
+    /// the scope it was written in from an empty one nested in it, so it runs in that.
+    fn new(body: Body<Code>) -> Frame {
+        let scope = if body.code.binds { Some(Scope::new(body.scope)) } else { body.scope };
+        Frame::Code { code: body.code, pc: 0, scope }
+    }
+
+    /// Runs the body of a function called by name. A body assigning names is given a
+    /// scope of its own even if it binds nothing itself, which keeps the assignments of
+    /// the quotations in it from reaching the global names.
+    fn call(body: Body<Code>) -> Frame {
+        let scope = match body.code.binds || body.code.assigns {
+            true => Some(Scope::call(body.scope)),
+            false => body.scope,
+        };
+        Frame::Code { code: body.code, pc: 0, scope }
+    }
+
+    fn global(code: Code) -> Frame {
+        Frame::Code { code, pc: 0, scope: None }
+    }
+}
+
+/// Compiles the input and runs it on the stack. Programs behave the same as with
+/// `interpreter::run`, but run faster: ops are looked up once when compiling, names
+/// are looked up by slot, and functions and quotations are compiled once and then
+/// shared by every call to them.
+///
+/// # Returns
+///
+/// True if all of the input ran, or false if it was stopped by an error.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::VecDeque;
+/// use bprog::interpreter::Env;
+/// use bprog::lexer::to_tokens;
+/// use bprog::parsed::Parsed;
+/// use bprog::parsing::parse;
+/// use bprog::stack::Stack;
+/// use bprog::vm::run;
+///
+/// let mut stack: Stack<Parsed> = Stack::new();
+/// let mut env = Env::new();
+/// let parsed = parse(&mut to_tokens("square { dup * } fun [ 1 2 3 ] 0 foldl { square + }")).unwrap();
+///
+/// assert!(run(&mut stack, &mut VecDeque::from(parsed), &mut env, false));
+/// assert_eq!("14", stack.contents_to_string());
+/// ```
+pub fn run(stack: &mut Stack<Parsed>, input: &mut VecDeque<Parsed>, env: &mut Env, fatal: bool) -> bool {
+    let code = compile(std::mem::take(input), &mut env.bindings);
+    transaction(stack, env, fatal, |stack, env| run_code(stack, code, env, fatal))
+}
+
+/// Runs compiled code on the stack. When an error occurs it is reported, panicking if
+/// `fatal` is set, and the stack is replaced by the value that caused it, without
+/// rolling anything back.
+pub fn run_code(stack: &mut Stack<Parsed>, code: Code, env: &mut Env, fatal: bool) -> bool {
+    run_frame(stack, Frame::global(code), env, fatal)
+}
+
+/// Compiles the input and runs it like `run`, binding its names in `scope` rather
+/// than globally.
+pub(crate) fn run_in(stack: &mut Stack<Parsed>, input: VecDeque<Parsed>, scope: Option<Rc<Scope>>, env: &mut Env,
+                     fatal: bool) -> bool {
+    let code = compile(input, &mut env.bindings);
+    run_frame(stack, Frame::Code { code, pc: 0, scope }, env, fatal)
+}
+
+fn run_frame(stack: &mut Stack<Parsed>, frame: Frame, env: &mut Env, fatal: bool) -> bool {
+    let mut frames = vec![frame];
+    while let Some(frame) = frames.last_mut() {
+        match frame {
+            Frame::Code { code, pc, scope } => {
+                if *pc == code.len() {
+                    frames.pop();
+                    continue;
+                }
+                env.enter(scope);
+                let (code, at) = (Rc::clone(code), *pc);
+                *pc += 1;
+                let next = match &code[at] {
+                    Instr::Stop(err) => {
+                        stack.push(Parsed::Error(err.clone()));
+                        return false;
+                    },
+                    instr => exec(instr, stack, &frames, env, fatal),
+                };
+                if let Some(next) = next {
+                    if let Err(err) = push_frame(&mut frames, next, env.depth_limit) {
+                        stack.push(Parsed::Error(err));
+                    }
+                }
+                if recover(&mut frames, stack, env.depth_limit).is_some() {
+                    return fail(stack, code[at].token(), fatal);
+                }
+            },
+            Frame::Iteration(iteration) => {
+                let next = iteration.advance(stack);
+                if let Some(Parsed::Error(_)) = stack.top() {
+                    let token = iteration.token().clone();
+                    if recover(&mut frames, stack, env.depth_limit).is_some() {
+                        return fail(stack, token, fatal);
+                    }
+                    continue;
+                }
+                match next {
+                    Some(body) => if let Err(err) = push_frame(&mut frames, Frame::new(body), env.depth_limit) {
+                        let token = token_of(frames.last().expect("iteration is still on top"));
+                        stack.push(Parsed::Error(err));
+                        if recover(&mut frames, stack, env.depth_limit).is_some() {
+                            return fail(stack, token, fatal);
+                        }
+                    },
+                    None => {
+                        frames.pop();
+                    },
+                }
+            },
+        }
+    }
+    true
+}
+
+/// Recovers from any error on top of the stack like the interpreter does, see
+/// `interpreter::run`. Returns the error, leaving it on the stack, if no frame guards
+/// the code that failed.
+fn recover(frames: &mut Vec<Frame>, stack: &mut Stack<Parsed>, limit: usize) -> Option<StackError> {
+    while let Some(Parsed::Error(err)) = stack.top() {
+        let err = err.clone();
+        let handler = loop {
+            match frames.last_mut() {
+                Some(Frame::Iteration(iteration)) => if let Some(handler) = iteration.catch(&err, stack) {
+                    break handler;
+                },
+                Some(_) => {},
+                None => return Some(err),
+            }
+            frames.pop();
+        };
+        if let Err(err) = push_frame(frames, Frame::new(handler), limit) {
+            stack.push(Parsed::Error(err));
+        }
+    }
+    None
+}
+
+/// Reports the error on top of the stack, and replaces the stack by the value that
+/// caused it.
+fn fail(stack: &mut Stack<Parsed>, token: Parsed, fatal: bool) -> bool {
+    if let Some(Parsed::Error(err)) = stack.top() {
+        let message = err.report(token.span());
+        if fatal { panic!("{}", message)} else { println!("{}", message)}
+    }
+    stack.clear();
+    stack.push(token);
+    false
+}
+
+fn token_of(frame: &Frame) -> Parsed {
+    match frame {
+        Frame::Code { .. } => Parsed::Void,
+        Frame::Iteration(iteration) => iteration.token().clone(),
+    }
+}
+
+/// Pushes a frame, first dropping the frame on top if it is code that has finished.
+fn push_frame(frames: &mut Vec<Frame>, frame: Frame, limit: usize) -> Result<(), StackError> {
+    if let Some(Frame::Code { code, pc, .. }) = frames.last() {
+        if *pc == code.len() {
+            frames.pop();
+        }
+    }
+    if limit > 0 && frames.len() >= limit {
+        return Err(StackError::DepthLimit(limit));
+    }
+    frames.push(frame);
+    Ok(())
+}
+
+/// Checks whether pushing one more frame would exceed the depth limit, see `push_frame`.
+fn at_depth_limit(frames: &[Frame], limit: usize) -> bool {
+    let finished = match frames.last() {
+        Some(Frame::Code { code, pc, .. }) => *pc == code.len(),
+        _ => false,
+    };
+    limit > 0 && frames.len() - finished as usize >= limit
+}
+
+/// Runs a single instruction, returning the frame of any code it calls.
+fn exec(instr: &Instr, stack: &mut Stack<Parsed>, frames: &[Frame], env: &mut Env, fatal: bool) -> Option<Frame> {
+    match instr {
+        Instr::Push(Parsed::Quotation(q, None)) => stack.push(Parsed::Quotation(Rc::clone(q), env.scope.clone())),
+        Instr::Push(value) => stack.push(value.clone()),
+        Instr::List { items, symbols, quotations } => {
+            let mut list = Rc::clone(items);
+            for (i, slot) in symbols {
+                let local = match (&env.scope, &items[*i]) {
+                    (Some(scope), Parsed::Symbol(name, _)) => scope.get(name),
+                    _ => None,
+                };
+                if let Some(binding) = local.as_ref().or(env.bindings.at(*slot)) {
+                    Rc::make_mut(&mut list)[*i] = binding.value.clone();
+                }
+            }
+            if env.scope.is_some() {
+                for i in quotations {
+                    if let Parsed::Quotation(q, None) = &items[*i] {
+                        Rc::make_mut(&mut list)[*i] = Parsed::Quotation(Rc::clone(q), env.scope.clone());
+                    }
+                }
+            }
+            stack.push(Parsed::List(list));
+        },
+        Instr::Symbol { slot, name, symbol } => {
+            if let Some(binding) = env.scope.as_ref().and_then(|scope| scope.get(name)) {
+                if binding.function {
+                    return iterate(stack, local_code(name, binding.value, env).map(Frame::call));
+                }
+                stack.push(binding.value);
+                return None;
+            }
+            match env.bindings.at(*slot) {
+                Some(binding) if binding.function => {
+                    return iterate(stack, bound_code(*slot, env).map(Frame::call));
+                },
+                Some(binding) => stack.push(binding.value.clone()),
+                None => stack.push(symbol.clone()),
+            }
+        },
+        // Modules are run like the program, so these need to know whether errors are fatal.
+        Instr::Op { op: op @ (Op::Import | Op::Use), signature, modifiers, token } => {
+            match check_modifiers(op, signature, modifiers, env) {
+                Ok(()) => match import(op, modifier_args(modifiers, env), token, env, run_in, fatal) {
+                    Parsed::Void => {},
+                    ret => stack.push(ret),
+                },
+                Err(err) => stack.push(Parsed::Error(err)),
+            }
+        },
+        Instr::Op { op, signature, modifiers, token } => {
+            return exec_op(op, signature, modifiers, token, stack, frames, env);
+        },
+        Instr::Stop(err) | Instr::Fail { error: err, .. } => stack.push(Parsed::Error(err.clone())),
+    }
+    None
+}
+
+fn exec_op(op: &Op, signature: &Signature, modifiers: &[Modifier], token: &Parsed,
+           stack: &mut Stack<Parsed>, frames: &[Frame], env: &mut Env) -> Option<Frame> {
+    if let Op::Locals(names) = op {
+        bind_locals(names, stack, env);
+        return None;
+    }
+    // Modifiers are checked before the stack arguments are popped, as in the interpreter.
+    if let Err(err) = check_modifiers(op, signature, modifiers, env) {
+        stack.push(Parsed::Error(err));
+        return None;
+    }
+    let mut arg  = Parsed::Error(StackError::PopEmpty);
+    let mut arg2 = Parsed::Error(StackError::PopEmpty);
+    if let Params::Unary(_) | Params::Binary(_, _) = &signature.stack_args {
+        if let Some(t) = stack.pop() {
+            arg = t
+        } else {
+            stack.push(Parsed::Error(StackError::PopEmpty));
+            return None;
+        }
+    }
+    if let Params::Binary(_, _) = &signature.stack_args {
+        if let Some(t) = stack.pop() {
+            arg2 = t
+        } else {
+            stack.push(Parsed::Error(StackError::PopEmpty));
+            return None;
+        }
+    }
+    let ret = match &signature.stack_args {
+        Params::Nullary => match op {
+            Op::Loop => return iterate(stack, modifier_code(&modifiers[0], env)
+                .and_then(|condition| Ok((condition, modifier_code(&modifiers[1], env)?)))
+                .map(|(condition, body)| Frame::Iteration(Iteration::until(condition, body, token)))),
+            Op::Global => return iterate(stack, modifier_code(&modifiers[0], env)
+                .map(|body| Frame::global(body.code))),
+            Op::Try | Op::Ensure => return iterate(stack, modifier_code(&modifiers[0], env)
+                .and_then(|body| Ok((body, modifier_code(&modifiers[1], env)?)))
+                .map(|(body, handler)| Frame::Iteration(match op {
+                    Op::Try => Iteration::try_catch(body, handler, token),
+                    _ => Iteration::ensure(body, handler, token),
+                }))),
+            _ => op.exec_nullary(modifier_args(modifiers, env), env),
+        },
+        Params::Unary(c) => {
+            if !c.is_satisfied_by(&arg.get_type()) {
+                Parsed::Error(arg_mismatch(op, &Args::Unary(arg), true))
+            } else {
+                match op {
+                    Op::If => {
+                        let branch = if arg == Parsed::Bool(true) { &modifiers[0] } else { &modifiers[1] };
+                        return iterate(stack, modifier_code(branch, env).map(Frame::new));
+                    },
+                    Op::Case => {
+                        let branch = if open_case(arg, stack) { &modifiers[0] } else { &modifiers[1] };
+                        return iterate(stack, modifier_code(branch, env).map(Frame::new));
+                    },
+                    Op::Times => return iterate(stack, modifier_code(&modifiers[0], env)
+                        .and_then(|body| Iteration::times(arg, body, token))
+                        .map(Frame::Iteration)),
+                    Op::Map => return iterate(stack, modifier_code(&modifiers[0], env)
+                        .map(|body| Frame::Iteration(Iteration::map(arg, body, token)))),
+                    Op::Each => return iterate(stack, modifier_code(&modifiers[0], env)
+                        .map(|body| Frame::Iteration(Iteration::each(arg, body, token)))),
+                    _ => op.exec_unary(arg, modifier_args(modifiers, env), env),
+                }
+            }
+        },
+        Params::Binary(c1, c2) => {
+            if !c1.is_satisfied_by(&arg2.get_type()) ||
+                !c2.is_satisfied_by(&arg.get_type()) {
+                Parsed::Error(arg_mismatch(op, &Args::Binary(arg2, arg), true))
+            } else if op == &Op::Foldl {
+                stack.push(arg);
+                return iterate(stack, modifier_code(&modifiers[0], env)
+                    .map(|body| Frame::Iteration(Iteration::each(arg2, body, token))));
+            } else {
+                op.exec_binary(arg2, arg, modifier_args(modifiers, env), env)
+            }
+        },
+        _ => panic!("temary arguments not implemented")
+    };
+
+    match ret {
+        Parsed::Quotation(q, _) if op.pushes_values() => q.iter().for_each(|p| stack.push(p.clone())),
+        Parsed::Quotation(q, scope) => return exec_quotation(q, scope, stack, frames, env),
+        Parsed::Void => {},
+        _ => stack.push(ret)
+    }
+    None
+}
+
+/// Runs a quotation returned by an op in a scope nested in `scope`. Quotations of
+/// values that evaluate to themselves are pushed without compiling them.
+fn exec_quotation(q: Rc<VecDeque<Parsed>>, scope: Option<Rc<Scope>>, stack: &mut Stack<Parsed>,
+                  frames: &[Frame], env: &mut Env) -> Option<Frame> {
+    let inert = q.iter().all(|p| match p {
+        Parsed::Void | Parsed::Num(_) | Parsed::String(_) | Parsed::Bool(_) | Parsed::Quotation(..) => true,
+        _ => false,
+    });
+    if !inert {
+        return Some(Frame::new(Body { code: compile(q.iter().cloned(), &mut env.bindings), scope }));
+    }
+    // Running them would take a frame, which may be one too many.
+    if at_depth_limit(frames, env.depth_limit) {
+        stack.push(Parsed::Error(StackError::DepthLimit(env.depth_limit)));
+    } else {
+        q.iter().for_each(|p| stack.push(match p {
+            Parsed::Quotation(items, None) => Parsed::Quotation(Rc::clone(items), scope.clone()),
+            p => p.clone(),
+        }));
+    }
+    None
+}
+
+/// Returns a frame, or pushes the error that prevented it.
+fn iterate(stack: &mut Stack<Parsed>, frame: Result<Frame, StackError>) -> Option<Frame> {
+    match frame {
+        Ok(frame) => Some(frame),
+        Err(err) => {
+            stack.push(Parsed::Error(err));
+            None
+        },
+    }
+}
+
+/// Returns the scope the code of a bound value runs in, see `body_of`.
+fn scope_of(value: &Parsed, env: &Env) -> Option<Rc<Scope>> {
+    match value {
+        Parsed::Quotation(_, scope) => scope.clone(),
+        _ => env.scope.clone(),
+    }
+}
+
+/// Returns the compiled code of the value bound to a slot, compiling it the first time.
+fn bound_code(slot: usize, env: &mut Env) -> Result<Body<Code>, StackError> {
+    let value = &env.bindings.at(slot)
+        .expect("bug: compiled code of an unbound slot requested")
+        .value;
+    let scope = scope_of(value, env);
+    if let Some(code) = env.bindings.compiled(slot) {
+        return Ok(Body { code: Rc::clone(code), scope });
+    }
+    let code = compile(body_of(value.clone(), env)?.code.iter().cloned(), &mut env.bindings);
+    env.bindings.set_compiled(slot, Rc::clone(&code));
+    Ok(Body { code, scope })
+}
+
+/// Returns the compiled code of a value bound to a name in the current scope, compiling
+/// it the first time.
+fn local_code(name: &Name, value: Parsed, env: &mut Env) -> Result<Body<Code>, StackError> {
+    let local = env.scope.clone().expect("bug: local binding found outside of a scope");
+    let scope = scope_of(&value, env);
+    if let Some(code) = local.compiled(name) {
+        return Ok(Body { code, scope });
+    }
+    let code = compile(body_of(value, env)?.code.iter().cloned(), &mut env.bindings);
+    local.set_compiled(name, Rc::clone(&code));
+    Ok(Body { code, scope })
+}
+
+/// Returns the name and binding of a symbol modifier bound in the current scope.
+fn local_binding<'a>(modifier: &'a Modifier, env: &Env) -> Option<(&'a Name, Binding)> {
+    match (&env.scope, modifier.slot, &modifier.value) {
+        (Some(scope), Some(_), Parsed::Symbol(name, _)) => Some((name, scope.get(name)?)),
+        _ => None,
+    }
+}
+
+/// Returns the value of a modifier, replacing bound symbols by their values and giving
+/// quotations the current scope.
+fn modifier_value(modifier: &Modifier, env: &Env) -> Parsed {
+    if let Some((_, binding)) = local_binding(modifier, env) {
+        return binding.value;
+    }
+    match (modifier.slot.and_then(|slot| env.bindings.at(slot)), &modifier.value) {
+        (Some(binding), _) => binding.value.clone(),
+        (None, Parsed::Quotation(q, None)) => Parsed::Quotation(Rc::clone(q), env.scope.clone()),
+        (None, value) => value.clone(),
+    }
+}
+
+fn modifier_type(modifier: &Modifier, env: &Env) -> Type {
+    if let Some((_, binding)) = local_binding(modifier, env) {
+        return binding.value.get_type();
+    }
+    match modifier.slot.and_then(|slot| env.bindings.at(slot)) {
+        Some(binding) => binding.value.get_type(),
+        None => modifier.value.get_type(),
+    }
+}
+
+/// Returns the code a modifier runs, using the code compiled for it where there is some.
+fn modifier_code(modifier: &Modifier, env: &mut Env) -> Result<Body<Code>, StackError> {
+    if let Some((name, binding)) = local_binding(modifier, env) {
+        return local_code(name, binding.value, env);
+    }
+    match (modifier.slot, &modifier.code) {
+        (Some(slot), _) if env.bindings.at(slot).is_some() => bound_code(slot, env),
+        (_, Some(code)) => Ok(Body { code: Rc::clone(code), scope: env.scope.clone() }),
+        _ => {
+            let body = body_of(modifier_value(modifier, env), env)?;
+            Ok(Body { code: compile(body.code.iter().cloned(), &mut env.bindings), scope: body.scope })
+        },
+    }
+}
+
+fn modifier_args(modifiers: &[Modifier], env: &Env) -> Args {
+    match modifiers {
+        [] => Args::Nullary,
+        [m] => Args::Unary(modifier_value(m, env)),
+        [m1, m2, ..] => Args::Binary(modifier_value(m1, env), modifier_value(m2, env)),
+    }
+}
+
+/// Checks the modifiers of an op against its signature, without copying them unless
+/// they do not satisfy it.
+fn check_modifiers(op: &Op, signature: &Signature, modifiers: &[Modifier], env: &Env) -> Result<(), StackError> {
+    let satisfied = match &signature.modifiers {
+        Params::Nullary => true,
+        Params::Unary(c) => c.is_satisfied_by(&modifier_type(&modifiers[0], env)),
+        Params::Binary(c1, c2) => c1.is_satisfied_by(&modifier_type(&modifiers[0], env)) &&
+            c2.is_satisfied_by(&modifier_type(&modifiers[1], env)),
+        _ => panic!("closure arguments defined for max 2 quotations")
+    };
+    if satisfied {
+        Ok(())
+    } else {
+        Err(arg_mismatch(op, &modifier_args(modifiers, env), false))
+    }
+}
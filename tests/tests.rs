@@ -384,6 +384,21 @@ mod test_list_quotations {
     fn test_foldl_div_short() {
         assert_eq!(t("[ 2 5 ] 20 foldl div"), "2");
     }
+
+    #[test]
+    fn test_map_keeps_quotations_in_list() {
+        assert_eq!(t("[ { 1 2 + } { 3 } ] map { exec }"), "[3,3]");
+    }
+
+    #[test]
+    fn test_map_over_strings_with_spaces() {
+        assert_eq!(t("[ \"  a  b  \" \" c\\nd \" ] map { length }"), "[6,3]");
+    }
+
+    #[test]
+    fn test_map_keeps_unbound_symbols() {
+        assert_eq!(t("[ foo bar ] map { }"), "[foo,bar]");
+    }
 }
 
 mod test_assignments {
@@ -534,6 +549,16 @@ mod test_times {
     fn test_times_condensed_addition() {
         assert_eq!(t("5 times 10 4 times +"), "50");
     }
+
+    #[test]
+    fn test_times_many_iterations() {
+        assert_eq!(t("0 100000 times { 1 + }"), "100000");
+    }
+
+    #[test]
+    fn test_times_zero_and_negative() {
+        assert_eq!(t("1 0 times { 1 + } -3 times { 1 + }"), "1");
+    }
 }
 
 mod test_loop {
@@ -553,6 +578,17 @@ mod test_loop {
     fn test_loop_with_conditional_length() {
         assert_eq!(t("[ 1 ] loop { dup length 9 > } { dup head 1 + swap cons }"), "[10,9,8,7,6,5,4,3,2,1]");
     }
+
+    #[test]
+    fn test_loop_many_iterations() {
+        assert_eq!(t("0 loop { dup 99999 > } { 1 + }"), "100000");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_loop_condition_not_bool() {
+        t("0 loop { \" no \" } { 1 + }");
+    }
 }
 
 mod test_functions {
@@ -577,9 +613,11 @@ mod test_functions {
                   4 toList"), "[1,2,3,4]");
     }
 
+    // These programs named a variable `max` before it became a math operation, and
+    // use `limit` instead since reserved names can not be bound.
     #[test]
     fn test_gen1to_num_function() {
-        assert_eq!(t("gen1toNum { max swap := 1 loop { dup max > } { dup 1 + } } fun \
+        assert_eq!(t("gen1toNum { limit swap := 1 loop { dup limit > } { dup 1 + } } fun \
                   3 gen1toNum + + +"), "10");
     }
 
@@ -587,7 +625,7 @@ mod test_functions {
     fn test_odd_to_list_gen1to_num_functions_combined() {
         assert_eq!(t("odd { dup 2 div swap 2 / == if False True } fun \
                   toList { [ ] swap times cons } fun \
-                  gen1toNum { max swap := 1 loop { dup max > } { dup 1 + } } fun \
+                  gen1toNum { limit swap := 1 loop { dup limit > } { dup 1 + } } fun \
                   4 gen1toNum 5 toList map odd"), "[True,False,True,False,True]");
     }
 
@@ -627,18 +665,1446 @@ mod test_extra { // other extras
 
     #[test]
     fn test_stack() {
-        use std::collections::{HashMap, VecDeque};
-        use bprog::interpreter::{Binding, run};
+        use std::collections::VecDeque;
+        use bprog::interpreter::{Env, run};
         use bprog::parsed::Parsed;
         use bprog::parsing::parse;
         use bprog::stack::Stack;
-        use bprog::utility::to_tokens;
+        use bprog::lexer::to_tokens;
 
         let input = "5 times 5 + +";
         let mut stack: Stack<Parsed> = Stack::new();
-        let mut dictionary: HashMap<String, Binding> = HashMap::new();
-        let parsed = parse(&mut VecDeque::from(to_tokens(&mut input.to_string())));
-        run(&mut stack, &mut VecDeque::from(parsed), &mut dictionary, true);
+        let mut env = Env::new();
+        let parsed = parse(&mut to_tokens(input)).unwrap();
+        run(&mut stack, &mut VecDeque::from(parsed), &mut env, true);
         assert_eq!("15 5 5", stack.contents_to_string())
     }
 }
+
+mod test_strings {
+    use bprog::t;
+
+    #[test]
+    fn test_string_touching_quotes() {
+        assert_eq!(t("\"hello\""), "\"hello\"")
+    }
+
+    #[test]
+    fn test_string_keeps_whitespace() {
+        assert_eq!(t("\" a  b\tc \" length"), "6")
+    }
+
+    #[test]
+    fn test_string_spaced_at_opening_quote() {
+        assert_eq!(t("\" hello\" length"), "6")
+    }
+
+    #[test]
+    fn test_string_spaced_at_closing_quote() {
+        assert_eq!(t("\"hello \" length"), "6")
+    }
+
+    #[test]
+    fn test_append_keeps_one_sided_space() {
+        assert_eq!(t("\"ab\" \" cd\" append"), "\"ab cd\"")
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        assert_eq!(t("\"say \\\"hi\\\"\\n\\u{263A}\""), "\"say \"hi\"\n\u{263A}\"")
+    }
+
+    #[test]
+    fn test_raw_string() {
+        assert_eq!(t("r#\"C:\\dir \"x\"\"#"), "\"C:\\dir \"x\"\"")
+    }
+
+    #[test]
+    fn test_multi_line_string() {
+        assert_eq!(t("\"one\ntwo\\\n    three\""), "\"one\ntwothree\"")
+    }
+
+    #[test]
+    fn test_string_survives_map() {
+        assert_eq!(t("[ \" a  \\\" \" ] map { \"!\" append }"), "[\"a  \"!\"]")
+    }
+}
+
+mod test_comments {
+    use bprog::t;
+
+    #[test]
+    fn test_line_comment() {
+        assert_eq!(t("1 -- a comment\n2 + -- another"), "3")
+    }
+
+    #[test]
+    fn test_block_comment() {
+        assert_eq!(t("1 {- spans\nlines -} 2 +"), "3")
+    }
+
+    #[test]
+    fn test_nested_block_comment() {
+        assert_eq!(t("1 {- outer {- inner -} still outer -} 2 +"), "3")
+    }
+
+    #[test]
+    fn test_comments_in_quotation_and_list() {
+        assert_eq!(t("[ 1 -- one\n 2 {- two -} ] map { 10 * -- scale\n }"), "[10,20]")
+    }
+
+    #[test]
+    fn test_comment_marker_in_string() {
+        assert_eq!(t("\" -- {- not a comment \""), "\"-- {- not a comment\"")
+    }
+}
+
+mod test_parse_errors {
+    use bprog::lexer::to_tokens;
+    use bprog::parse_error::ParseError;
+    use bprog::parsing::parse;
+
+    fn errors(input: &str) -> Vec<ParseError> {
+        parse(&mut to_tokens(input)).unwrap_err()
+            .errors().into_iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_unclosed_bracket() {
+        assert!(matches!(errors("1 { 2")[..], [ParseError::UnclosedBracket(_, _)]));
+    }
+
+    #[test]
+    fn test_unmatched_bracket() {
+        assert!(matches!(errors("1 } 2")[..], [ParseError::UnmatchedBracket(_, _)]));
+    }
+
+    #[test]
+    fn test_mismatched_bracket() {
+        assert!(matches!(errors("{ 1 ]")[..], [ParseError::MismatchedBracket { .. }]));
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        assert!(matches!(errors("1 \" abc")[..], [ParseError::UnterminatedString(_)]));
+    }
+
+    #[test]
+    fn test_all_errors_reported() {
+        let found = errors("] { [ 1 } \"\\q\" {- open");
+        assert!(matches!(found[..], [
+            ParseError::UnmatchedBracket(_, _),
+            ParseError::UnclosedBracket(_, _),
+            ParseError::InvalidEscape(_, _),
+            ParseError::UnterminatedComment(_),
+        ]));
+    }
+
+    #[test]
+    fn test_error_reported_by_t() {
+        let report = bprog::t("1 } 2\n{ [ 3 }");
+        assert!(report.contains("<input>:1:3"));
+        assert!(report.contains("<input>:2:3"));
+    }
+}
+
+mod test_spans {
+    use bprog::t;
+
+    #[test]
+    #[should_panic(expected = "<input>:2:9")]
+    fn test_error_location() {
+        t("1 2\n  \" a \" +");
+    }
+
+    #[test]
+    #[should_panic(expected = "<input>:1:13")]
+    fn test_error_location_inside_function() {
+        t("bad { \" x \" + } fun\n1 bad");
+    }
+
+    #[test]
+    fn test_unterminated_string_location() {
+        assert!(t("\" unterminated").contains("1 | \" unterminated"));
+    }
+}
+
+mod test_big_integers {
+    use bprog::t;
+
+    #[test]
+    fn test_literal_beyond_i128() {
+        assert_eq!(t("1000000000000000000000000000000000000000000"), "1000000000000000000000000000000000000000000")
+    }
+
+    #[test]
+    fn test_addition_past_i128() {
+        assert_eq!(t("170141183460469231731687303715884105727 1 +"), "170141183460469231731687303715884105728")
+    }
+
+    #[test]
+    fn test_result_shrinks_back() {
+        assert_eq!(t("170141183460469231731687303715884105728 1 - 170141183460469231731687303715884105727 =="), "True")
+    }
+
+    #[test]
+    fn test_multiplication_exact() {
+        assert_eq!(t("x 4294967296 := x x * x * x * x * 1 +"), "1461501637330902918203684832716283019655932542977")
+    }
+
+    #[test]
+    fn test_precision_above_f64() {
+        assert_eq!(t("9007199254740993 1 +"), "9007199254740994")
+    }
+
+    #[test]
+    fn test_integer_division() {
+        assert_eq!(t("100000000000000000000000000000000000000000 3 div"), "33333333333333333333333333333333333333333")
+    }
+
+    #[test]
+    fn test_modulo() {
+        assert_eq!(t("100000000000000000000000000000000000000000 7 %"), "5")
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert_eq!(t("100000000000000000000000000000000000000001 100000000000000000000000000000000000000000 >"), "True")
+    }
+
+    #[test]
+    fn test_parse_integer() {
+        assert_eq!(t("\" -99999999999999999999999999999999999999999 \" parseInteger 1 -"), "-100000000000000000000000000000000000000000")
+    }
+}
+
+mod test_rationals {
+    use bprog::t;
+
+    #[test]
+    fn test_literal() {
+        assert_eq!(t("2/6"), "1/3")
+    }
+
+    #[test]
+    fn test_literal_whole() {
+        assert_eq!(t("4/2"), "2")
+    }
+
+    #[test]
+    fn test_exact_addition() {
+        assert_eq!(t("1/3 1/6 +"), "1/2")
+    }
+
+    #[test]
+    fn test_result_becomes_integer() {
+        assert_eq!(t("1/3 3 *"), "1")
+    }
+
+    #[test]
+    fn test_division_with_integer() {
+        assert_eq!(t("1/3 3 /"), "1/9")
+    }
+
+    #[test]
+    fn test_integer_division_stays_float() {
+        assert_eq!(t("1 3 /"), "0.3333333333333333")
+    }
+
+    #[test]
+    fn test_float_operand_gives_float() {
+        assert_eq!(t("1/4 0.5 +"), "0.75")
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert_eq!(t("1/3 1/4 >"), "True")
+    }
+
+    #[test]
+    fn test_equality() {
+        assert_eq!(t("2/6 1/3 == 1/2 0.5 == &&"), "True")
+    }
+
+    #[test]
+    fn test_numerator_and_denominator() {
+        assert_eq!(t("-3/4 numerator 10 * -3/4 denominator +"), "-26")
+    }
+
+    #[test]
+    fn test_denominator_of_integer() {
+        assert_eq!(t("7 denominator"), "1")
+    }
+
+    #[test]
+    fn test_to_float() {
+        assert_eq!(t("3/8 toFloat"), "0.375")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_numerator_of_float() {
+        t("0.5 numerator");
+    }
+}
+
+mod test_overflow {
+    use bprog::t;
+
+    const MAX: &str = "170141183460469231731687303715884105727";
+
+    #[test]
+    fn test_default_policy() {
+        assert_eq!(t("overflow"), "\"bigint\"")
+    }
+
+    #[test]
+    fn test_switch_policy() {
+        assert_eq!(t("\" saturate \" setOverflow overflow"), "\"saturate\"")
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(t(&format!("\" wrap \" setOverflow {} 1 +", MAX)), "-170141183460469231731687303715884105728")
+    }
+
+    #[test]
+    fn test_saturate() {
+        assert_eq!(t(&format!("\" saturate \" setOverflow {} -1 * 2 *", MAX)), "-170141183460469231731687303715884105728")
+    }
+
+    #[test]
+    fn test_float() {
+        assert_eq!(t(&format!("\" float \" setOverflow {} {} *", MAX, MAX)), "28948022309329050000000000000000000000000000000000000000000000000000000000000.0")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_error() {
+        t(&format!("\" error \" setOverflow {} 1 +", MAX));
+    }
+
+    #[test]
+    fn test_error_policy_allows_results_that_fit() {
+        assert_eq!(t(&format!("\" error \" setOverflow {} 1 - 1 +", MAX)), MAX)
+    }
+
+    #[test]
+    fn test_exact_above_f64_precision() {
+        assert_eq!(t("\" error \" setOverflow 9007199254740993 3 *"), "27021597764222979")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unknown_policy() {
+        t("\" sometimes \" setOverflow");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_division() {
+        t("1 0 div");
+    }
+}
+
+mod test_formatter {
+    use bprog::formatter::format_program;
+    use bprog::t;
+
+    fn format(input: &str) -> String {
+        format_program(input).unwrap()
+    }
+
+    #[test]
+    fn test_spacing_normalised() {
+        assert_eq!(format("  1   2\t+  "), "1 2 +\n")
+    }
+
+    #[test]
+    fn test_nested_quotations_indented() {
+        assert_eq!(format("f {\nif { 1 }\n{\n2\n}\n} fun"), "f {\n    if { 1 }\n    {\n        2\n    }\n} fun\n")
+    }
+
+    #[test]
+    fn test_long_list_broken() {
+        let list = format!("[ {} ]", (1..=40).map(|i| i.to_string()).collect::<Vec<_>>().join(" "));
+        let formatted = format(&list);
+        assert!(formatted.starts_with("[\n    1 2 3"));
+        assert!(formatted.lines().all(|line| line.len() <= 80));
+        assert!(formatted.ends_with("\n]\n"));
+    }
+
+    #[test]
+    fn test_comments_kept() {
+        assert_eq!(format("1 -- one\n{- two\n  lines -}  2 + -- sum"), "1 -- one\n{- two\n  lines -} 2 + -- sum\n")
+    }
+
+    #[test]
+    fn test_blank_lines_collapsed() {
+        assert_eq!(format("\n\n1\n\n\n\n2 +\n\n"), "1\n\n2 +\n")
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let program = std::fs::read_to_string("prelude.bprog").unwrap();
+        let formatted = format(&program);
+        assert_eq!(formatted, format(&formatted))
+    }
+
+    #[test]
+    fn test_behaviour_preserved() {
+        for program in [
+            "[ 1 -- one\n 2 {- two -} ] map { 10 * -- scale\n }",
+            "[ r#\"say \"hi\"\"# \"\\tend\\u{21}\" \" spaced  \" ]",
+            "x 3 :=\n\n   [ 1 2 ]   map {\n x + }",
+            "\"  \" length \"\" length +",
+            "[ \" lead\" \"trail \" \"  both  \" \" \" ]",
+        ] {
+            assert_eq!(t(program), t(&format(program)), "{}", program)
+        }
+    }
+
+    #[test]
+    fn test_one_sided_strings_round_trip() {
+        let program = "[ \" lead\" \"trail \" \" \" ]";
+        assert_eq!(format(&format(program)), format(program))
+    }
+
+    #[test]
+    fn test_parse_error_returned() {
+        assert!(format_program("{ 1 ]").is_err())
+    }
+}
+
+mod test_math {
+    use bprog::t;
+
+    #[test]
+    fn test_abs() {
+        assert_eq!(t("-5 abs"), "5");
+        assert_eq!(t("-3/4 abs"), "3/4");
+        assert_eq!(t("-2.5 abs"), "2.5")
+    }
+
+    #[test]
+    fn test_abs_of_smallest_integer() {
+        assert_eq!(t("-170141183460469231731687303715884105728 abs"), "170141183460469231731687303715884105728")
+    }
+
+    #[test]
+    fn test_min_max() {
+        assert_eq!(t("3 2.5 min"), "2.5");
+        assert_eq!(t("1/2 4 max"), "4")
+    }
+
+    #[test]
+    fn test_pow_exact() {
+        assert_eq!(t("2 10 pow"), "1024");
+        assert_eq!(t("2/3 2 pow"), "4/9");
+        assert_eq!(t("2 -2 pow"), "1/4")
+    }
+
+    #[test]
+    fn test_pow_float() {
+        assert_eq!(t("4 0.5 pow"), "2.0");
+        assert_eq!(t("2.0 3 pow"), "8.0")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_pow_zero_to_negative() {
+        t("0 -1 pow");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_pow_negative_to_fraction() {
+        t("-8 1/3 pow");
+    }
+
+    #[test]
+    fn test_sqrt() {
+        assert_eq!(t("16 sqrt"), "4.0");
+        assert_eq!(t("1/4 sqrt"), "0.5")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sqrt_negative() {
+        t("-1 sqrt");
+    }
+
+    #[test]
+    fn test_exp_ln_log10() {
+        assert_eq!(t("0 exp"), "1.0");
+        assert_eq!(t("1 ln"), "0.0");
+        assert_eq!(t("1000 log10"), "3.0")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ln_zero() {
+        t("0 ln");
+    }
+
+    #[test]
+    fn test_trig() {
+        assert_eq!(t("0 sin"), "0.0");
+        assert_eq!(t("0 cos"), "1.0");
+        assert_eq!(t("1 asin pi 2 / =="), "True")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_acos_out_of_range() {
+        t("2 acos");
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(t("-2.5 floor"), "-3");
+        assert_eq!(t("-2.5 ceil"), "-2");
+        assert_eq!(t("-2.5 round"), "-3");
+        assert_eq!(t("-2.5 truncate"), "-2")
+    }
+
+    #[test]
+    fn test_rounding_rationals() {
+        assert_eq!(t("7/2 floor"), "3");
+        assert_eq!(t("7/2 round"), "4");
+        assert_eq!(t("-7/2 truncate"), "-3")
+    }
+
+    #[test]
+    fn test_rounding_large_float() {
+        assert_eq!(t("1e30 floor"), "1000000000000000019884624838656")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rounding_infinity() {
+        t("1e308 10.0 * floor");
+    }
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(t("pi"), "3.141592653589793");
+        assert_eq!(t("e"), "2.718281828459045")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_math_on_non_numbers() {
+        t("\" four \" sqrt");
+    }
+}
+
+mod test_integer_ops {
+    use bprog::t;
+
+    #[test]
+    fn test_bitwise() {
+        assert_eq!(t("12 10 band"), "8");
+        assert_eq!(t("12 10 bor"), "14");
+        assert_eq!(t("12 10 bxor"), "6");
+        assert_eq!(t("5 bnot"), "-6")
+    }
+
+    #[test]
+    fn test_bitwise_negative() {
+        assert_eq!(t("-1 255 band"), "255")
+    }
+
+    #[test]
+    fn test_shifts() {
+        assert_eq!(t("1 10 shl"), "1024");
+        assert_eq!(t("-7 1 shr"), "-4")
+    }
+
+    #[test]
+    fn test_shift_past_i128() {
+        assert_eq!(t("1 127 shl"), "170141183460469231731687303715884105728")
+    }
+
+    #[test]
+    fn test_shift_with_wrap_policy() {
+        assert_eq!(t("\" wrap \" setOverflow 1 127 shl"), "-170141183460469231731687303715884105728")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_negative_shift() {
+        t("1 -1 shl");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_too_large_shift() {
+        t("1 99999999999 shr");
+    }
+
+    #[test]
+    fn test_popcount() {
+        assert_eq!(t("255 popcount"), "8")
+    }
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(t("12 -18 gcd"), "6");
+        assert_eq!(t("-4 6 lcm"), "12")
+    }
+
+    #[test]
+    fn test_isqrt() {
+        assert_eq!(t("17 isqrt"), "4");
+        assert_eq!(t("100000000000000000000000000000000000000000 isqrt"), "316227766016837933199")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_isqrt_negative() {
+        t("-4 isqrt");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bitwise_rejects_floats() {
+        t("1.0 1 band");
+    }
+
+    #[test]
+    fn test_radix_literals() {
+        assert_eq!(t("0xff 0b101 + 0o17 + -0x10 +"), "259")
+    }
+
+    #[test]
+    fn test_radix_printing() {
+        assert_eq!(t("255 toHex"), "\"0xff\"");
+        assert_eq!(t("-5 toBinary"), "\"-0b101\"");
+        assert_eq!(t("8 toOctal"), "\"0o10\"")
+    }
+
+    #[test]
+    fn test_parse_radix_integer() {
+        assert_eq!(t("\" 0x1F \" parseInteger"), "31")
+    }
+}
+
+mod test_random {
+    use bprog::t;
+
+    #[test]
+    fn test_same_seed_same_numbers() {
+        assert_eq!(t("7 seed 0 1000000 randInt 7 seed 0 1000000 randInt =="), "True");
+        assert_eq!(t("7 seed randFloat 7 seed randFloat =="), "True")
+    }
+
+    #[test]
+    fn test_default_seed_is_fixed() {
+        assert_eq!(t("1 6 randInt"), "3");
+        assert_eq!(t("[ 1 2 3 4 5 ] shuffle"), "[4,5,2,3,1]")
+    }
+
+    #[test]
+    fn test_rand_int_in_range() {
+        assert_eq!(t("[ ] 100 times { -3 3 randInt swap cons } map { dup -3 < swap 3 > || } \
+                  False foldl { || }"), "False")
+    }
+
+    #[test]
+    fn test_rand_int_single_value() {
+        assert_eq!(t("5 5 randInt"), "5")
+    }
+
+    #[test]
+    fn test_rand_int_large_range() {
+        assert_eq!(t("1 seed 0 1 200 shl randInt 0 1 200 shl randInt =="), "False")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rand_int_bounds_out_of_order() {
+        t("5 1 randInt");
+    }
+
+    #[test]
+    fn test_rand_float_in_range() {
+        assert_eq!(t("randFloat dup 0.0 < swap 1.0 < not ||"), "False")
+    }
+
+    #[test]
+    fn test_shuffle_keeps_elements() {
+        assert_eq!(t("3 seed [ 1 2 3 4 5 ] shuffle 0 foldl { + }"), "15")
+    }
+
+    #[test]
+    fn test_sample() {
+        assert_eq!(t("[ 1 2 3 4 5 ] 3 sample length"), "3");
+        assert_eq!(t("[ 1 2 3 ] 0 sample"), "[]")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sample_too_many() {
+        t("[ 1 2 ] 3 sample");
+    }
+}
+
+mod test_recursion {
+    use bprog::t;
+
+    #[test]
+    fn test_deep_recursion() {
+        assert_eq!(t("sum { dup 0 == if { } { dup 1 - sum + } } fun 100000 sum"), "5000050000")
+    }
+
+    #[test]
+    fn test_tail_calls_run_in_constant_space() {
+        assert_eq!(t("10 setDepthLimit \
+                  countdown { dup 0 > if { 1 - countdown } { } } fun 100000 countdown"), "0")
+    }
+
+    #[test]
+    fn test_recursion_inside_iteration() {
+        assert_eq!(t("fact { dup 1 > if { dup 1 - fact * } { pop 1 } } fun \
+                  [ 1 2 3 4 5 ] map { fact }"), "[1,2,6,24,120]")
+    }
+
+    #[test]
+    fn test_default_depth_limit() {
+        assert_eq!(t("depthLimit"), "1000000")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_depth_limit_exceeded() {
+        t("100 setDepthLimit sum { dup 0 == if { } { dup 1 - sum + } } fun 1000 sum");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_negative_depth_limit() {
+        t("-1 setDepthLimit");
+    }
+
+    #[test]
+    fn test_no_depth_limit() {
+        assert_eq!(t("0 setDepthLimit sum { dup 0 == if { } { dup 1 - sum + } } fun 1000 sum"), "500500")
+    }
+}
+
+mod test_compiled {
+    use bprog::t;
+
+    #[test]
+    fn test_rebound_function() {
+        assert_eq!(t("f { 1 + } fun g { f } fun 1 g ' f { 10 * } fun g"), "20")
+    }
+
+    #[test]
+    fn test_function_bound_after_use_is_compiled() {
+        assert_eq!(t("g { 2 f } fun f { 3 * } fun g"), "6")
+    }
+
+    #[test]
+    fn test_rebound_modifier() {
+        assert_eq!(t("body { 1 + } := 0 2 times body ' body { 10 * } := 3 times body"), "2000")
+    }
+
+    #[test]
+    fn test_list_symbols_resolved_when_run() {
+        assert_eq!(t("l { [ x 2 ] } fun x 1 := l ' x 3 := l append"), "[1,2,3,2]")
+    }
+
+    #[test]
+    fn test_quoted_symbol_not_resolved() {
+        assert_eq!(t("x 1 := ' x"), "x")
+    }
+
+    #[test]
+    fn test_function_bound_to_op() {
+        assert_eq!(t("add [ + ] head fun 1 2 add"), "3")
+    }
+
+    #[test]
+    fn test_dup_does_not_evaluate_symbol() {
+        assert_eq!(t("x 5 := ' x dup"), "x")
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_missing_modifier() {
+        t("1 2 + times");
+    }
+}
+
+mod test_shared_values {
+    use bprog::t;
+
+    #[test]
+    fn test_cons_leaves_copy_unchanged() {
+        assert_eq!(t("[ 1 2 3 ] dup 0 swap cons pop"), "[1,2,3]")
+    }
+
+    #[test]
+    fn test_append_leaves_binding_unchanged() {
+        assert_eq!(t("l [ 1 2 ] := l [ 3 ] append pop l"), "[1,2]")
+    }
+
+    #[test]
+    fn test_tail_leaves_binding_unchanged() {
+        assert_eq!(t("l [ 1 2 3 ] := l tail pop l"), "[1,2,3]")
+    }
+
+    #[test]
+    fn test_shuffle_leaves_binding_unchanged() {
+        assert_eq!(t("l [ 1 2 3 4 5 ] := l shuffle pop l"), "[1,2,3,4,5]")
+    }
+
+    #[test]
+    fn test_list_literal_in_function_unchanged() {
+        assert_eq!(t("f { [ 1 ] } fun f [ 2 ] append pop f"), "[1]")
+    }
+
+    #[test]
+    fn test_long_list_built_in_place() {
+        assert_eq!(t("[ ] 20000 times { [ 1 ] append } length"), "20000")
+    }
+
+    #[test]
+    fn test_dup_of_long_list() {
+        assert_eq!(t("[ ] 20000 times { [ 1 ] append } 20000 times { dup pop } length"), "20000")
+    }
+}
+
+mod test_stack_storage {
+    use bprog::stack::Stack;
+
+    fn numbers(n: i32) -> Stack<i32> {
+        (1..=n).collect()
+    }
+
+    #[test]
+    fn test_peek_past_bottom() {
+        let stack = numbers(3);
+        assert_eq!(Some(&1), stack.peek(2));
+        assert_eq!(None, stack.peek(3));
+    }
+
+    #[test]
+    fn test_split_off_too_many() {
+        let mut stack = numbers(3);
+        assert_eq!(None, stack.split_off(4));
+        assert_eq!(3, stack.size());
+    }
+
+    #[test]
+    fn test_split_off_all() {
+        let mut stack = numbers(3);
+        assert_eq!(Some(vec![1, 2, 3]), stack.split_off(3));
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut stack = numbers(5);
+        stack.truncate(2);
+        assert_eq!("2 1", stack.contents_to_string());
+        stack.truncate(4);
+        assert_eq!(2, stack.size());
+    }
+
+    #[test]
+    fn test_iterators_agree() {
+        let stack = numbers(4);
+        let from_top: Vec<&i32> = stack.iter().collect();
+        let from_bottom: Vec<&i32> = stack.iter_from_bottom().rev().collect();
+        assert_eq!(from_top, from_bottom);
+        assert_eq!(4, stack.iter().len());
+    }
+
+    #[test]
+    fn test_large_stack() {
+        let mut stack = numbers(1_000_000);
+        assert_eq!(Some(&1), stack.peek(999_999));
+        stack.clear();
+        assert_eq!(None, stack.top());
+    }
+}
+
+mod test_closures {
+    use bprog::t;
+
+    #[test]
+    fn test_function_binding_is_local() {
+        assert_eq!(t("f { x 2 := x } fun f x"), "x")
+    }
+
+    #[test]
+    fn test_function_leaves_caller_binding() {
+        assert_eq!(t("x 1 := f { ' x 2 := x } fun f pop x"), "1")
+    }
+
+    #[test]
+    fn test_nested_quotation_sees_binding() {
+        assert_eq!(t("f { a 3 := 2 times { a } + } fun f"), "6")
+    }
+
+    #[test]
+    fn test_callee_does_not_see_caller_binding() {
+        assert_eq!(t("x 1 := g { x } fun f { ' x 2 := g } fun f"), "1")
+    }
+
+    #[test]
+    fn test_recursion_with_locals() {
+        assert_eq!(t("fact { n swap := n 1 > if { n 1 - fact n * } { 1 } } fun 5 fact"), "120")
+    }
+
+    #[test]
+    fn test_escaping_quotation_captures() {
+        assert_eq!(t("adder { n swap := { n + } } fun 2 adder ' add2 swap fun 40 add2"), "42")
+    }
+
+    #[test]
+    fn test_closure_sees_later_binding() {
+        assert_eq!(t("f { { y } y 5 := exec } fun f"), "5")
+    }
+
+    #[test]
+    fn test_local_function() {
+        assert_eq!(t("f { sq { dup * } fun 3 sq } fun f pop sq"), "sq")
+    }
+
+    #[test]
+    fn test_assign_updates_enclosing_local() {
+        assert_eq!(t("f { acc 0 := 5 times { ' acc acc 1 + := } acc } fun f"), "5")
+    }
+
+    #[test]
+    fn test_assign_updates_global() {
+        assert_eq!(t("counter 0 := 5 times { ' counter counter 1 + := } counter"), "5")
+    }
+
+    #[test]
+    fn test_assign_in_if_updates_global() {
+        assert_eq!(t("x 1 := True if { ' x 2 := } { } x"), "2")
+    }
+
+    #[test]
+    fn test_assign_in_if_updates_enclosing_local() {
+        assert_eq!(t("f { x 1 := True if { ' x 2 := } { } x } fun f"), "2")
+    }
+
+    #[test]
+    fn test_helper_leaves_global() {
+        assert_eq!(t("x 1 := helper { ' x 99 := 5 } fun helper x +"), "6")
+    }
+
+    #[test]
+    fn test_helper_body_leaves_global() {
+        assert_eq!(t("x 1 := helper { 1 times { ' x 99 := } 5 } fun helper x +"), "6")
+    }
+
+    #[test]
+    fn test_caller_local_left_by_helper() {
+        assert_eq!(t("f { ' x 2 := x } fun g { x 1 := f pop x } fun g"), "1")
+    }
+
+    #[test]
+    fn test_top_level_quotation_run_by_function() {
+        assert_eq!(t("x 1 := run { exec } fun { ' x 2 := } run x"), "2")
+    }
+
+    #[test]
+    fn test_assign_unbound_stays_local() {
+        assert_eq!(t("f { True if { y 2 := } { } y } fun f"), "y")
+    }
+
+    #[test]
+    fn test_local_function_sees_locals() {
+        assert_eq!(t("f { n 3 := g { n 2 * } fun g } fun f"), "6")
+    }
+
+    #[test]
+    fn test_local_quotation_escapes() {
+        assert_eq!(t("mk { n -> add { n + } := add } fun 2 mk ' add2 swap fun 40 add2"), "42")
+    }
+
+    #[test]
+    fn test_scope_binding_own_quotation_is_dropped() {
+        use std::collections::VecDeque;
+        use std::rc::Rc;
+        use bprog::interpreter::Binding;
+        use bprog::name::Name;
+        use bprog::parsed::Parsed;
+        use bprog::scope::Scope;
+
+        let scope = Scope::new(None);
+        let helper = Parsed::Quotation(Rc::new(VecDeque::new()), Some(Rc::clone(&scope)));
+        scope.bind(Name::new("g"), Binding { function: true, constant: false, value: helper });
+        assert_eq!(1, Rc::strong_count(&scope));
+        let found = scope.get(&Name::new("g")).map(|binding| binding.value);
+        assert!(matches!(found, Some(Parsed::Quotation(_, Some(captured))) if Rc::ptr_eq(&captured, &scope)));
+
+        let dropped = Rc::downgrade(&scope);
+        drop(scope);
+        assert!(dropped.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_global_binds_globally() {
+        assert_eq!(t("f { global { ' y 7 := } } fun f y"), "7")
+    }
+
+    #[test]
+    fn test_global_reads_shadowed() {
+        assert_eq!(t("x 1 := f { ' x 2 := global { x } } fun f"), "1")
+    }
+}
+
+mod test_locals {
+    use bprog::t;
+
+    #[test]
+    fn test_header_binds_top_values() {
+        assert_eq!(t("3 4 { a b -> a b - } exec"), "-1")
+    }
+
+    #[test]
+    fn test_recursion_with_parameters() {
+        assert_eq!(t("fibo { n -> n 2 < if { n } { n 1 - fibo n 2 - fibo + } } fun 15 fibo"), "610")
+    }
+
+    #[test]
+    fn test_parameters_do_not_leak() {
+        assert_eq!(t("sub { a b -> a b - } fun 10 3 sub pop a"), "a")
+    }
+
+    #[test]
+    fn test_map_with_parameter() {
+        assert_eq!(t("[ 1 2 3 ] map { x -> x x * }"), "[1,4,9]")
+    }
+
+    #[test]
+    fn test_foldl_with_parameters() {
+        assert_eq!(t("[ 1 2 3 ] 0 foldl { acc x -> acc x 10 * + }"), "60")
+    }
+
+    #[test]
+    fn test_assign_parameter_in_times() {
+        assert_eq!(t("f { n -> 3 times { ' n n 2 * := } n } fun 1 f"), "8")
+    }
+
+    #[test]
+    fn test_assign_accumulator_in_each() {
+        assert_eq!(t("sum 0 := [ 1 2 3 ] each { x -> ' sum sum x + := } sum"), "6")
+    }
+
+    #[test]
+    fn test_header_written_back() {
+        assert_eq!(t("{ a b -> a }"), "{ a b -> a }")
+    }
+
+    #[test]
+    fn test_header_only_of_names() {
+        assert_eq!(t("{ x 1 -> x } length"), "4")
+    }
+
+    #[test]
+    #[should_panic(expected = "<input>:1:9")]
+    fn test_too_few_values() {
+        t("f { a b -> a } fun 1 f");
+    }
+}
+
+mod test_modules {
+    use bprog::t;
+
+    #[test]
+    fn test_qualified_name() {
+        assert_eq!(t("import tests/modules/geometry 3 geometry.square"), "9")
+    }
+
+    #[test]
+    fn test_module_uses_own_definitions() {
+        assert_eq!(t("import tests/modules/geometry 3 4 geometry.sumsq"), "25")
+    }
+
+    #[test]
+    fn test_import_keeps_names_qualified() {
+        assert_eq!(t("import tests/modules/geometry square"), "square")
+    }
+
+    #[test]
+    fn test_module_keeps_global_name() {
+        assert_eq!(t("limit 1 := import tests/modules/config config.limit limit +"), "11")
+    }
+
+    #[test]
+    fn test_use_binds_unqualified() {
+        assert_eq!(t("use tests/modules/geometry 3 square"), "9")
+    }
+
+    #[test]
+    fn test_relative_import() {
+        assert_eq!(t("loads 0 := import tests/modules/a 5 a.a"), "10")
+    }
+
+    #[test]
+    fn test_module_loads_once() {
+        assert_eq!(t("loads 0 := import tests/modules/a import tests/modules/b import tests/modules/b loads"), "1")
+    }
+
+    #[test]
+    #[should_panic(expected = "import cycle")]
+    fn test_import_cycle() {
+        t("import tests/modules/cycle_a");
+    }
+
+    #[test]
+    #[should_panic(expected = "no module")]
+    fn test_missing_module() {
+        t("import tests/modules/missing");
+    }
+}
+
+mod test_packages {
+    use bprog::module::Modules;
+    use bprog::package::Package;
+    use bprog::t_search;
+
+    fn modules() -> Modules {
+        let mut modules = Modules::default();
+        modules.search_path.push("tests/packages".into());
+        modules
+    }
+
+    fn t_packages(input: &str) -> String {
+        t_search(input, &["tests/packages"])
+    }
+
+    #[test]
+    fn test_import_package() {
+        assert_eq!(t_packages("import shapes 2 shapes.area"), "12")
+    }
+
+    #[test]
+    fn test_import_package_module() {
+        assert_eq!(t_packages("import shapes/circle 1 1 2 circle.inside"), "True")
+    }
+
+    #[test]
+    fn test_resolves_to_package() {
+        let path = modules().find("shapes", None).unwrap();
+        assert!(path.ends_with("tests/packages/shapes/shapes.bprog"));
+        assert_eq!(Package::containing(&path).unwrap().version, "0.3.1");
+    }
+
+    #[test]
+    fn test_missing_dependency() {
+        assert!(modules().find("broken", None).unwrap_err().contains("missing, which is not installed"));
+    }
+
+    #[test]
+    #[should_panic(expected = "package sneaky does not depend on vectors")]
+    fn test_undeclared_dependency() {
+        t_packages("import sneaky");
+    }
+
+    #[test]
+    fn test_package_module_cannot_leave_package() {
+        assert!(modules().find("shapes/../vectors/vectors", None).unwrap_err().contains("leaves"));
+        assert!(modules().find("shapes//tmp/circle", None).unwrap_err().contains("leaves"));
+    }
+
+    #[test]
+    fn test_search_path_module_cannot_leave_directory() {
+        assert!(modules().find("../packages/vectors/vectors", None).unwrap_err().contains("leaves"));
+    }
+
+    #[test]
+    #[should_panic(expected = "module ../vectors/vectors is outside of package sneaky")]
+    fn test_relative_import_cannot_leave_package() {
+        t_packages("import sneaky/escape");
+    }
+
+    #[test]
+    fn test_installed_packages() {
+        let names: Vec<String> = Package::installed(&modules().search_path).into_iter()
+            .map(|package| package.unwrap().name)
+            .collect();
+        assert_eq!(names, ["broken", "shapes", "sneaky", "vectors"]);
+    }
+}
+
+mod test_try {
+    use bprog::t;
+
+    #[test]
+    fn test_handler_gets_error() {
+        assert_eq!(t("try { 1 0 div } { errorMessage }"), "\"err: zero division\"")
+    }
+
+    #[test]
+    fn test_body_without_error() {
+        assert_eq!(t("try { 1 } { pop 2 }"), "1")
+    }
+
+    #[test]
+    fn test_stack_restored() {
+        assert_eq!(t("1 2 try { pop pop 1 0 div } { pop 42 } + +"), "45")
+    }
+
+    #[test]
+    fn test_error_in_function() {
+        assert_eq!(t("f { [ ] head } fun try { f } { errorMessage }"), "\"err: head of an empty list\"")
+    }
+
+    #[test]
+    fn test_error_in_iteration() {
+        assert_eq!(t("try { [ 1 2 ] map { 0 div } } { pop 9 }"), "9")
+    }
+
+    #[test]
+    fn test_err_payload() {
+        assert_eq!(t("try { err [ 1 2 ] } { errorPayload }"), "[1,2]")
+    }
+
+    #[test]
+    fn test_throw_payload() {
+        assert_eq!(t("try { 5 throw } { errorPayload 1 + }"), "6")
+    }
+
+    #[test]
+    fn test_rethrow() {
+        assert_eq!(t("try { try { 1 0 div } { throw } } { errorMessage }"), "\"err: zero division\"")
+    }
+
+    #[test]
+    fn test_caught_error_displayed() {
+        assert_eq!(t("try { err \" oops \" } { }"), "oops")
+    }
+
+    #[test]
+    fn test_ensure_after_body() {
+        assert_eq!(t("ensure { 1 } { 2 } +"), "3")
+    }
+
+    #[test]
+    fn test_ensure_after_error() {
+        assert_eq!(t("n 0 := try { ensure { 1 0 div } { global { ' n 7 := } } } { pop } n"), "7")
+    }
+
+    #[test]
+    #[should_panic(expected = "zero division")]
+    fn test_ensure_raises_again() {
+        t("ensure { 1 0 div } { 2 }");
+    }
+
+    #[test]
+    #[should_panic(expected = "does not satisfy constraint in the function")]
+    fn test_handler_error_not_caught() {
+        t("try { 1 0 div } { head }");
+    }
+}
+
+mod test_maybe {
+    use bprog::t;
+
+    #[test]
+    fn test_safe_parse_integer() {
+        assert_eq!(t("\" 12 \" safeParseInteger"), "Ok 12")
+    }
+
+    #[test]
+    fn test_safe_parse_failure() {
+        assert_eq!(t("\" x1 \" safeParseInteger"), "Failure \"cannot parse 'x1' as an integer\"")
+    }
+
+    #[test]
+    fn test_safe_parse_float() {
+        assert_eq!(t("\" 2.5 \" safeParseFloat"), "Ok 2.5")
+    }
+
+    #[test]
+    fn test_safe_head() {
+        assert_eq!(t("[ 1 2 ] safeHead"), "Just 1")
+    }
+
+    #[test]
+    fn test_safe_head_empty() {
+        assert_eq!(t("[ ] safeHead"), "Nothing")
+    }
+
+    #[test]
+    fn test_safe_tail() {
+        assert_eq!(t("[ 4 5 ] safeTail"), "Just [5]")
+    }
+
+    #[test]
+    fn test_unwrap_or() {
+        assert_eq!(t("\" x \" safeParseInteger 0 unwrapOr \" 3 \" safeParseInteger 0 unwrapOr +"), "3")
+    }
+
+    #[test]
+    fn test_map_just() {
+        assert_eq!(t("\" 2.5 \" safeParseFloat map { 2 * }"), "Ok 5.0")
+    }
+
+    #[test]
+    fn test_map_nothing() {
+        assert_eq!(t("nothing map { 1 + }"), "Nothing")
+    }
+
+    #[test]
+    fn test_map_failure() {
+        assert_eq!(t("\" e \" failure map { 1 + }"), "Failure \"e\"")
+    }
+
+    #[test]
+    fn test_case_just() {
+        assert_eq!(t("[ 3 ] safeHead case { 10 * } { 0 }"), "30")
+    }
+
+    #[test]
+    fn test_case_nothing() {
+        assert_eq!(t("[ ] safeHead case { 10 * } { 0 }"), "0")
+    }
+
+    #[test]
+    fn test_case_failure_reason() {
+        assert_eq!(t("\" x \" safeParseInteger case { 1 + } { length }"), "30")
+    }
+
+    #[test]
+    fn test_equality() {
+        assert_eq!(t("5 just 5 just =="), "True")
+    }
+}
+
+mod test_rollback {
+    use bprog::interpreter::OnError;
+    use bprog::t_session;
+
+    #[test]
+    fn test_error_keeps_stack() {
+        assert_eq!(t_session(&["1 2", "3 [ ] head"], OnError::Rollback), "2 1")
+    }
+
+    #[test]
+    fn test_error_in_quotation_keeps_stack() {
+        assert_eq!(t_session(&["1", "[ 1 2 ] map { [ ] head }"], OnError::Rollback), "1")
+    }
+
+    #[test]
+    fn test_error_unbinds_names() {
+        assert_eq!(t_session(&["y 5 := sq { dup * } fun [ ] head", "y sq"], OnError::Rollback), "sq y")
+    }
+
+    #[test]
+    fn test_error_keeps_bindings() {
+        assert_eq!(t_session(&["x 10 :=", "x' 3 := [ ] head", "x"], OnError::Rollback), "10")
+    }
+
+    #[test]
+    fn test_bind_after_error() {
+        assert_eq!(t_session(&["z 1 := w 2 := [ ] head", "w 3 :=", "w"], OnError::Rollback), "3")
+    }
+
+    #[test]
+    fn test_clear_on_error() {
+        assert_eq!(t_session(&["x 10 := 1 2", "3 [ ] head", "x"], OnError::Clear), "10 head")
+    }
+}
+
+mod test_debugger {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use bprog::debugger::Debugger;
+    use bprog::interpreter::{run, Env};
+    use bprog::lexer::to_tokens;
+    use bprog::parsing::parse;
+    use bprog::stack::Stack;
+
+    /// Runs the input in a debugger given the commands, returning the stack left and
+    /// the texts the debugger showed.
+    fn debug(input: &str, commands: &[&str]) -> (String, Vec<String>) {
+        let shown = Rc::new(RefCell::new(vec![]));
+        let mut commands: VecDeque<String> = commands.iter().map(|c| c.to_string()).collect();
+        let mut env = Env::new();
+        let texts = Rc::clone(&shown);
+        env.debugger = Some(Debugger::new(Box::new(move |text| {
+            texts.borrow_mut().push(text.to_string());
+            commands.pop_front()
+        })));
+        let mut stack = Stack::new();
+        let parsed = parse(&mut to_tokens(input)).unwrap();
+        run(&mut stack, &mut VecDeque::from(parsed), &mut env, true);
+        let shown = shown.borrow().clone();
+        (stack.contents_to_string(), shown)
+    }
+
+    #[test]
+    fn test_steps_every_token() {
+        let (stack, shown) = debug("1 2 +", &["s", "s", "s"]);
+        assert_eq!(stack, "3");
+        assert_eq!(shown.len(), 3);
+        assert!(shown[2].contains("next  > +") && shown[2].contains("stack > 2 1"));
+    }
+
+    #[test]
+    fn test_step_into_function() {
+        let (_, shown) = debug("sq { dup * } fun 3 sq 1 +", &["s"; 9]);
+        assert!(shown[5].contains("next  > dup") && shown[5].contains("calls > sq"));
+    }
+
+    #[test]
+    fn test_step_over_function() {
+        let (_, shown) = debug("sq { dup * } fun 3 sq 1 +", &["n"; 7]);
+        assert!(shown[5].contains("next  > 1") && shown[5].contains("stack > 9"));
+    }
+
+    #[test]
+    fn test_step_out_of_function() {
+        let (_, shown) = debug("f { 1 2 + } fun f 10 *", &["s", "s", "s", "s", "o"]);
+        assert!(shown[4].contains("calls > f"));
+        assert!(shown[5].contains("next  > 10") && shown[5].contains("stack > 3"));
+    }
+
+    #[test]
+    fn test_shows_pending_input() {
+        let (_, shown) = debug("1 2 + 3 *", &[]);
+        assert!(shown[0].contains("input > 2 + 3 *"));
+    }
+
+    #[test]
+    fn test_symbol_breakpoint() {
+        let (_, shown) = debug("sq { dup * } fun 3 sq 1 +", &["b sq", "c"]);
+        assert_eq!(shown[1], "breakpoint at sq");
+        assert!(shown[2].contains("next  > sq") && shown[2].contains("stack > 3"));
+    }
+
+    #[test]
+    fn test_line_breakpoint() {
+        let (_, shown) = debug("1\n2\n3 +\n+", &["b 3", "c"]);
+        assert!(shown[2].contains("next  > +") && shown[2].contains("stack > 3 2 1"));
+    }
+
+    #[test]
+    fn test_inspect_binding() {
+        let (_, shown) = debug("x 7 := x", &["n", "n", "n", "p x"]);
+        assert_eq!(shown[4], "x = 7");
+    }
+
+    #[test]
+    fn test_modify_binding() {
+        assert_eq!(debug("x 1 := x 10 *", &["n", "n", "n", "set x 5", "c"]).0, "50")
+    }
+
+    #[test]
+    fn test_modify_local_binding() {
+        let (stack, _) = debug("f { y -> y 2 * } fun 4 f", &["b y", "c", "set y 21", "c"]);
+        assert_eq!(stack, "42")
+    }
+}